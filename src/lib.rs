@@ -13,10 +13,15 @@ extern crate float_cmp;
 #[macro_use]
 extern crate bencher;
 
-#[cfg(test)]
+#[cfg(any(test, feature = "testing"))]
+pub mod test_observer;
+#[cfg(any(test, feature = "testing"))]
 pub mod test_scheduler;
 
 pub mod impl_helper;
+#[cfg(any(test, feature = "testing"))]
+pub mod marble;
+pub mod notification;
 pub mod observable;
 pub mod observer;
 pub mod ops;
@@ -29,6 +34,7 @@ pub mod type_hint;
 
 pub mod prelude {
 
+  pub use crate::notification::*;
   pub use crate::observable;
   pub use crate::observable::*;
   pub use crate::observer;
@@ -0,0 +1,442 @@
+//! Marble diagram testing, enabled by the feature `testing`.
+//!
+//! A marble diagram describes a timeline of notifications, every character
+//! takes one frame of the virtual time:
+//!
+//! - `-`: a frame passes without notification.
+//! - `a`-`z`, `0`-`9` ...: emits the value mapped by the character.
+//! - `|`: completes.
+//! - `#`: errors.
+//! - `(ab)`: emits all the notifications in the group at the same frame.
+//! - `^`: the subscription point of a hot observable, the frame zero.
+//! - whitespace: ignored, useful for aligning diagrams.
+//!
+//! ```rust
+//! # use rxrust::prelude::*;
+//! use rxrust::marble::MarbleTest;
+//!
+//! let m = MarbleTest::new();
+//! let source = m.cold("-a-b-|", &[('a', 1), ('b', 2)]);
+//! m.expect_observable(source.map(|v| v * 10))
+//!   .to_be("-a-b-|", &[('a', 10), ('b', 20)]);
+//! ```
+use crate::prelude::*;
+use crate::scheduler::Instant;
+use crate::test_scheduler::TestScheduler;
+use std::fmt::{Debug, Write};
+use std::time::Duration;
+
+type Timeline<Item, Err> = Vec<(usize, Notification<Item, Err>)>;
+
+/// The context of a marble test, all its observables share one
+/// [`TestScheduler`] and one start time.
+pub struct MarbleTest {
+  scheduler: TestScheduler,
+  start: Instant,
+  frame: Duration,
+}
+
+impl Default for MarbleTest {
+  #[inline]
+  fn default() -> Self { Self::new() }
+}
+
+impl MarbleTest {
+  /// Creates a marble test, every frame takes one millisecond of virtual
+  /// time.
+  #[inline]
+  pub fn new() -> Self { Self::with_frame(Duration::from_millis(1)) }
+
+  /// Creates a marble test, every frame takes `frame` of virtual time.
+  pub fn with_frame(frame: Duration) -> Self {
+    let scheduler = TestScheduler::default();
+    let start = scheduler.now();
+    MarbleTest {
+      scheduler,
+      start,
+      frame,
+    }
+  }
+
+  /// The scheduler driving this test, pass it to time-based operators.
+  #[inline]
+  pub fn scheduler(&self) -> TestScheduler { self.scheduler.clone() }
+
+  /// The virtual time a frame takes.
+  #[inline]
+  pub fn frame(&self) -> Duration { self.frame }
+
+  /// Creates a cold observable, which plays `marble` from the frame it is
+  /// subscribed.
+  pub fn cold<Item: Clone>(
+    &self,
+    marble: &str,
+    values: &[(char, Item)],
+  ) -> ColdObservable<Item, ()> {
+    self.cold_timeline(marble, values, None)
+  }
+
+  /// Like [`MarbleTest::cold`], but `#` emits `err`.
+  pub fn cold_with_error<Item: Clone, Err: Clone>(
+    &self,
+    marble: &str,
+    values: &[(char, Item)],
+    err: Err,
+  ) -> ColdObservable<Item, Err> {
+    self.cold_timeline(marble, values, Some(err))
+  }
+
+  /// Creates a hot observable, which plays `marble` from the start of the
+  /// test whether subscribed or not. The notifications before `^` happened
+  /// before the test started, so no observer receives them.
+  pub fn hot<Item>(
+    &self,
+    marble: &str,
+    values: &[(char, Item)],
+  ) -> LocalSubject<'static, Item, ()>
+  where
+    Item: Clone + 'static,
+  {
+    self.hot_timeline(marble, values, None)
+  }
+
+  /// Like [`MarbleTest::hot`], but `#` emits `err`.
+  pub fn hot_with_error<Item, Err>(
+    &self,
+    marble: &str,
+    values: &[(char, Item)],
+    err: Err,
+  ) -> LocalSubject<'static, Item, Err>
+  where
+    Item: Clone + 'static,
+    Err: Clone + 'static,
+  {
+    self.hot_timeline(marble, values, Some(err))
+  }
+
+  /// Subscribes `observable` now and records its notifications, check them
+  /// by [`Expectation::to_be`].
+  pub fn expect_observable<S>(
+    &self,
+    observable: S,
+  ) -> Expectation<S::Item, S::Err>
+  where
+    S: LocalObservable<'static>,
+    S::Item: 'static,
+    S::Err: 'static,
+  {
+    let actual = MutRc::own(vec![]);
+    let observer = TimelineObserver {
+      timeline: actual.clone(),
+      scheduler: self.scheduler.clone(),
+      start: self.start,
+      frame: self.frame,
+    };
+    observable.actual_subscribe(observer);
+    Expectation {
+      scheduler: self.scheduler.clone(),
+      actual,
+    }
+  }
+
+  /// Runs the scheduler until every scheduled notification is emitted.
+  #[inline]
+  pub fn flush(&self) { self.scheduler.flush() }
+
+  fn cold_timeline<Item: Clone, Err: Clone>(
+    &self,
+    marble: &str,
+    values: &[(char, Item)],
+    err: Option<Err>,
+  ) -> ColdObservable<Item, Err> {
+    let (sub_frame, timeline) = parse_marble(marble, values, err);
+    assert!(
+      sub_frame.is_none(),
+      "cold observable does not support the subscription point `^`."
+    );
+    ColdObservable {
+      timeline,
+      scheduler: self.scheduler.clone(),
+      frame: self.frame,
+    }
+  }
+
+  fn hot_timeline<Item, Err>(
+    &self,
+    marble: &str,
+    values: &[(char, Item)],
+    err: Option<Err>,
+  ) -> LocalSubject<'static, Item, Err>
+  where
+    Item: Clone + 'static,
+    Err: Clone + 'static,
+  {
+    let (sub_frame, timeline) = parse_marble(marble, values, err);
+    let sub_frame = sub_frame.unwrap_or(0);
+    let subject = LocalSubject::new();
+    for (frame, notification) in timeline {
+      if frame < sub_frame {
+        continue;
+      }
+      let mut subject = subject.clone();
+      let delay = (frame - sub_frame) as u32 * self.frame;
+      LocalScheduler::schedule(
+        &self.scheduler,
+        move |n: Notification<Item, Err>| n.accept(&mut subject),
+        Some(delay),
+        notification,
+      );
+    }
+    subject
+  }
+}
+
+/// The notifications recorded by [`MarbleTest::expect_observable`].
+pub struct Expectation<Item, Err> {
+  scheduler: TestScheduler,
+  actual: MutRc<Timeline<Item, Err>>,
+}
+
+impl<Item, Err> Expectation<Item, Err>
+where
+  Item: Clone + PartialEq + Debug,
+  Err: Clone + PartialEq + Debug,
+{
+  /// Flushes the scheduler, and asserts the recorded notifications match
+  /// `marble`, panics with a diff of the two timelines if not.
+  pub fn to_be(self, marble: &str, values: &[(char, Item)]) {
+    self.assert_timeline(marble, values, None)
+  }
+
+  /// Like [`Expectation::to_be`], but `#` expects the error `err`.
+  pub fn to_be_with_error(
+    self,
+    marble: &str,
+    values: &[(char, Item)],
+    err: Err,
+  ) {
+    self.assert_timeline(marble, values, Some(err))
+  }
+
+  fn assert_timeline(
+    self,
+    marble: &str,
+    values: &[(char, Item)],
+    err: Option<Err>,
+  ) {
+    self.scheduler.flush();
+    let (_, expected) = parse_marble(marble, values, err);
+    let actual = self.actual.rc_deref();
+    if expected != *actual {
+      panic!(
+        "marble timelines are not equal, `-` expected, `+` actual:\n{}",
+        diff(&expected, &actual)
+      );
+    }
+  }
+}
+
+#[derive(Clone)]
+pub struct ColdObservable<Item, Err> {
+  timeline: Timeline<Item, Err>,
+  scheduler: TestScheduler,
+  frame: Duration,
+}
+
+impl<Item, Err> Observable for ColdObservable<Item, Err> {
+  type Item = Item;
+  type Err = Err;
+}
+
+impl<Item, Err> LocalObservable<'static> for ColdObservable<Item, Err>
+where
+  Item: 'static,
+  Err: 'static,
+{
+  type Unsub = LocalSubscription;
+
+  fn actual_subscribe<O>(self, observer: O) -> Self::Unsub
+  where
+    O: Observer<Item = Self::Item, Err = Self::Err> + 'static,
+  {
+    let subscription = LocalSubscription::default();
+    let observer = MutRc::own(observer);
+    for (frame, notification) in self.timeline {
+      let mut observer = observer.clone();
+      subscription.add(LocalScheduler::schedule(
+        &self.scheduler,
+        move |n: Notification<Item, Err>| n.accept(&mut observer),
+        Some(frame as u32 * self.frame),
+        notification,
+      ));
+    }
+    subscription
+  }
+}
+
+struct TimelineObserver<Item, Err> {
+  timeline: MutRc<Timeline<Item, Err>>,
+  scheduler: TestScheduler,
+  start: Instant,
+  frame: Duration,
+}
+
+impl<Item, Err> TimelineObserver<Item, Err> {
+  fn record(&self, notification: Notification<Item, Err>) {
+    let elapsed = self.scheduler.now().saturating_duration_since(self.start);
+    let frame = (elapsed.as_nanos() / self.frame.as_nanos()) as usize;
+    self.timeline.rc_deref_mut().push((frame, notification));
+  }
+}
+
+impl<Item, Err> Observer for TimelineObserver<Item, Err> {
+  type Item = Item;
+  type Err = Err;
+
+  fn next(&mut self, value: Item) { self.record(Notification::Next(value)) }
+
+  fn error(&mut self, err: Err) { self.record(Notification::Error(err)) }
+
+  fn complete(&mut self) { self.record(Notification::Complete) }
+}
+
+/// Parses `marble` to a timeline, returns it with the frame of `^` if any.
+fn parse_marble<Item: Clone, Err: Clone>(
+  marble: &str,
+  values: &[(char, Item)],
+  err: Option<Err>,
+) -> (Option<usize>, Timeline<Item, Err>) {
+  let mut timeline = vec![];
+  let mut sub_frame = None;
+  let mut group: Option<usize> = None;
+  for (frame, c) in marble.chars().filter(|c| !c.is_whitespace()).enumerate() {
+    let at = group.unwrap_or(frame);
+    match c {
+      '-' => {}
+      '^' => sub_frame = Some(frame),
+      '(' => group = Some(frame),
+      ')' => group = None,
+      '|' => timeline.push((at, Notification::Complete)),
+      '#' => {
+        let err = err
+          .clone()
+          .expect("`#` in marble requires an error value, use `*_with_error`.");
+        timeline.push((at, Notification::Error(err)));
+      }
+      c => {
+        let value = values
+          .iter()
+          .find(|(k, _)| *k == c)
+          .map(|(_, v)| v.clone())
+          .unwrap_or_else(|| panic!("no value for `{}` in marble.", c));
+        timeline.push((at, Notification::Next(value)));
+      }
+    }
+  }
+  (sub_frame, timeline)
+}
+
+fn diff<Item, Err>(
+  expected: &[(usize, Notification<Item, Err>)],
+  actual: &[(usize, Notification<Item, Err>)],
+) -> String
+where
+  Item: PartialEq + Debug,
+  Err: PartialEq + Debug,
+{
+  let mut out = String::new();
+  let (mut e, mut a) = (expected.iter().peekable(), actual.iter().peekable());
+  loop {
+    let (line, sign) = match (e.peek(), a.peek()) {
+      (None, None) => break,
+      (Some(x), Some(y)) if x == y => {
+        a.next();
+        (e.next().unwrap(), ' ')
+      }
+      (Some(x), Some(y)) if x.0 > y.0 => (a.next().unwrap(), '+'),
+      (Some(_), _) => (e.next().unwrap(), '-'),
+      (None, Some(_)) => (a.next().unwrap(), '+'),
+    };
+    let _ = writeln!(out, "{} frame {:>3}: {:?}", sign, line.0, line.1);
+  }
+  out
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn cold() {
+    let m = MarbleTest::new();
+    let source = m.cold("-a-(bc)-|", &[('a', 1), ('b', 2), ('c', 3)]);
+    m.expect_observable(source.map(|v| v * 10))
+      .to_be("-a-(bc)-|", &[('a', 10), ('b', 20), ('c', 30)]);
+  }
+
+  #[test]
+  fn cold_subscribed_later() {
+    let m = MarbleTest::new();
+    let source = m.cold("-a|", &[('a', 1)]);
+    m.scheduler().advance_by(m.frame() * 2);
+    m.expect_observable(source).to_be("---a|", &[('a', 1)]);
+  }
+
+  #[test]
+  fn hot() {
+    let m = MarbleTest::new();
+    let source = m.hot("-a-^-b-c-|", &[('a', 1), ('b', 2), ('c', 3)]);
+    m.scheduler().advance_by(m.frame() * 3);
+    m.expect_observable(source).to_be("----c-|", &[('c', 3)]);
+  }
+
+  #[test]
+  fn hot_before_subscription_point() {
+    let m = MarbleTest::new();
+    let source = m.hot("-a-^-b-|", &[('a', 1), ('b', 2)]);
+    m.expect_observable(source).to_be("--b-|", &[('b', 2)]);
+
+    let m = MarbleTest::new();
+    let source = m.hot("-|^-a-|", &[('a', 1)]);
+    m.expect_observable(source).to_be("--a-|", &[('a', 1)]);
+  }
+
+  #[test]
+  fn error() {
+    let m = MarbleTest::new();
+    let source = m.cold_with_error("-a-#", &[('a', 1)], "oops");
+    m.expect_observable(source.map(|v| v + 1)).to_be_with_error(
+      "-a-#",
+      &[('a', 2)],
+      "oops",
+    );
+  }
+
+  #[test]
+  fn debounce() {
+    let m = MarbleTest::new();
+    let source =
+      m.cold("-a--bc---d|", &[('a', 1), ('b', 2), ('c', 3), ('d', 4)]);
+    m.expect_observable(source.debounce(m.frame() * 2, m.scheduler()))
+      .to_be("---a---c--(d|)", &[('a', 1), ('c', 3), ('d', 4)]);
+  }
+
+  #[test]
+  #[should_panic(expected = "marble timelines are not equal")]
+  fn mismatch() {
+    let m = MarbleTest::new();
+    let source = m.cold("-a-|", &[('a', 1)]);
+    m.expect_observable(source).to_be("--a|", &[('a', 1)]);
+  }
+
+  #[test]
+  fn diff_lines() {
+    let expected: Timeline<i32, ()> =
+      vec![(1, Notification::Next(1)), (3, Notification::Complete)];
+    let actual = vec![(2, Notification::Next(1)), (3, Notification::Complete)];
+    assert_eq!(
+      diff(&expected, &actual),
+      "- frame   1: Next(1)\n+ frame   2: Next(1)\n  frame   3: Complete\n"
+    );
+  }
+}
@@ -0,0 +1,155 @@
+use crate::prelude::*;
+
+/// Represents a notification an Observable delivers to its observer: `next`,
+/// `error` or `complete`.
+///
+/// Use [`Observable::materialize`] to turn a stream into a stream of
+/// notifications, and [`Observable::dematerialize`] for the reverse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Notification<Item, Err> {
+  Next(Item),
+  Error(Err),
+  Complete,
+}
+
+impl<Item, Err> Notification<Item, Err> {
+  /// Delivers this notification to `observer`.
+  pub fn accept<O>(self, observer: &mut O)
+  where
+    O: Observer<Item = Item, Err = Err> + ?Sized,
+  {
+    match self {
+      Notification::Next(v) => observer.next(v),
+      Notification::Error(err) => observer.error(err),
+      Notification::Complete => observer.complete(),
+    }
+  }
+
+  /// Returns `true` if this is a terminal notification, `error` or `complete`.
+  #[inline]
+  pub fn is_terminal(&self) -> bool { !matches!(self, Notification::Next(_)) }
+}
+
+/// An observer adapter which accepts `Notification`s and feeds them into the
+/// wrapped observer. Notifications after a terminal one are ignored.
+#[derive(Clone)]
+pub struct NotificationObserver<O> {
+  observer: O,
+  is_stopped: bool,
+}
+
+impl<O> NotificationObserver<O> {
+  #[inline]
+  pub fn new(observer: O) -> Self {
+    NotificationObserver {
+      observer,
+      is_stopped: false,
+    }
+  }
+}
+
+impl<O: Observer> Observer for NotificationObserver<O> {
+  type Item = Notification<O::Item, O::Err>;
+  type Err = ();
+
+  fn next(&mut self, value: Self::Item) {
+    if !self.is_stopped {
+      self.is_stopped = value.is_terminal();
+      value.accept(&mut self.observer);
+    }
+  }
+
+  /// A stream of notifications carries its errors by `Notification::Error`,
+  /// so an error of the notification stream itself just stops the observer.
+  fn error(&mut self, _err: ()) { self.complete() }
+
+  fn complete(&mut self) {
+    if !self.is_stopped {
+      self.is_stopped = true;
+      self.observer.complete();
+    }
+  }
+}
+
+/// An observer adapter which wraps every notification it receives in a
+/// `Notification` and emits it by `next` to the wrapped observer. It never
+/// emits an error, the terminal notification is followed by `complete`.
+#[derive(Clone)]
+pub struct MaterializeObserver<O>(O);
+
+impl<O> MaterializeObserver<O> {
+  #[inline]
+  pub fn new(observer: O) -> Self { MaterializeObserver(observer) }
+}
+
+impl<O, Item, Err> Observer for MaterializeObserver<O>
+where
+  O: Observer<Item = Notification<Item, Err>>,
+{
+  type Item = Item;
+  type Err = Err;
+
+  #[inline]
+  fn next(&mut self, value: Item) { self.0.next(Notification::Next(value)) }
+
+  fn error(&mut self, err: Err) {
+    self.0.next(Notification::Error(err));
+    self.0.complete();
+  }
+
+  fn complete(&mut self) {
+    self.0.next(Notification::Complete);
+    self.0.complete();
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn feed_notifications() {
+    let mut values = vec![];
+    let mut errors = vec![];
+    let mut completed = 0;
+    {
+      let subject = LocalSubject::new();
+      subject.clone().subscribe_all(
+        |v| values.push(v),
+        |e| errors.push(e),
+        || completed += 1,
+      );
+      let mut observer = NotificationObserver::new(subject);
+      observer.next(Notification::Next(1));
+      observer.next(Notification::Next(2));
+      observer.next(Notification::Error("err"));
+      observer.next(Notification::Next(3));
+      observer.next(Notification::Complete);
+    }
+
+    assert_eq!(values, vec![1, 2]);
+    assert_eq!(errors, vec!["err"]);
+    assert_eq!(completed, 0);
+  }
+
+  #[test]
+  fn wrap_notifications() {
+    let mut notifications = vec![];
+    let mut completed = 0;
+    {
+      let subject = LocalSubject::new();
+      subject
+        .clone()
+        .subscribe_complete(|n| notifications.push(n), || completed += 1);
+      let mut observer = MaterializeObserver::new(subject);
+      observer.next(1);
+      observer.error("err");
+    }
+
+    assert_eq!(
+      notifications,
+      vec![Notification::Next(1), Notification::Error("err")]
+    );
+    assert_eq!(completed, 1);
+  }
+}
@@ -15,7 +15,7 @@ pub mod interval;
 pub use interval::{interval, interval_at};
 
 pub(crate) mod connectable_observable;
-pub use connectable_observable::{Connect, ConnectableObservable};
+pub use connectable_observable::{Connect, ConnectableObservable, Publish};
 
 mod observable_block_all;
 #[cfg(test)]
@@ -31,6 +31,9 @@ pub use from_fn::*;
 pub mod timer;
 pub use timer::{timer, timer_at};
 
+pub mod cron;
+pub use cron::cron;
+
 pub mod start;
 pub use start::start;
 
@@ -44,14 +47,24 @@ mod defer;
 mod observable_comp;
 pub use defer::*;
 
+pub mod using;
+pub use using::using;
+
+pub mod from_callback;
+pub use from_callback::*;
+
 use crate::prelude::*;
 pub use observable_comp::*;
 
 use crate::ops::default_if_empty::DefaultIfEmptyOp;
 use crate::ops::distinct::{DistinctKeyOp, DistinctUntilKeyChangedOp};
 use crate::ops::pairwise::PairwiseOp;
+use crate::ops::share::{InnerShare, ShareConfig, ShareOp};
 use crate::ops::tap::TapOp;
+use crate::scheduler::Instant;
+use connectable_observable::{PublishBehaviorSubject, PublishLastSubject};
 use ops::{
+  Accum, AverageOp, CountOp, FlatMapOp, MinMaxOp, ReduceOp, SumOp,
   box_it::{BoxOp, IntoBox},
   buffer::{BufferWithCountOp, BufferWithCountOrTimerOp, BufferWithTimeOp},
   combine_latest::CombineLatestOp,
@@ -64,10 +77,11 @@ use ops::{
   filter_map::FilterMapOp,
   finalize::FinalizeOp,
   flatten::FlattenOp,
-  group_by::GroupByOp,
+  group_by::{GroupByOp, GroupByUntilOp, GroupObservable},
   last::LastOp,
   map::MapOp,
   map_to::MapToOp,
+  materialize::{DematerializeOp, MaterializeOp},
   merge::MergeOp,
   merge_all::MergeAllOp,
   observe_on::ObserveOnOp,
@@ -84,16 +98,24 @@ use ops::{
   take_until::TakeUntilOp,
   take_while::TakeWhileOp,
   throttle_time::{ThrottleEdge, ThrottleTimeOp},
+  time_interval::TimeIntervalOp,
+  timestamp::TimestampOp,
+  unsubscribe_on::UnsubscribeOnOp,
   with_latest_from::WithLatestFromOp,
   zip::ZipOp,
-  Accum, AverageOp, CountOp, FlatMapOp, MinMaxOp, ReduceOp, SumOp,
 };
 use std::ops::{Add, Mul};
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 type ALLOp<O, F> =
   DefaultIfEmptyOp<TakeOp<FilterOp<MapOp<O, F>, fn(&bool) -> bool>>>;
 
+type ShareWithOp<Src, Sbj, F, SD> = ShareOp<
+  <Sbj as SubjectLike<Src>>::Rc<
+    InnerShare<Src, Sbj, F, SD, <Sbj as SubjectLike<Src>>::Connection>,
+  >,
+>;
+
 pub trait Observable: Sized {
   type Item;
   type Err;
@@ -264,6 +286,11 @@ pub trait Observable: Sized {
   /// Each emitted Observable emits items matching the key returned
   /// by the discriminator function.
   ///
+  /// The source is subscribed once, and its items are routed to a `Subject`
+  /// per key, `LocalSubject` or `SharedSubject` depending on the context of
+  /// the subscription. A group only emits the items that came after it was
+  /// subscribed, so subscribe it when it's emitted.
+  ///
   /// # Example
   ///
   /// ```
@@ -294,13 +321,104 @@ pub trait Observable: Sized {
   /// //  Gregory
   /// ```
   #[inline]
-  fn group_by<D, Item, Key>(self, discr: D) -> GroupByOp<Self, D>
+  fn group_by<D, Item, Key, Subject>(
+    self,
+    discr: D,
+  ) -> GroupByOp<Self, D, fn(Item) -> Item, Subject>
+  where
+    D: FnMut(&Item) -> Key,
+  {
+    self.group_by_element(discr, std::convert::identity)
+  }
+
+  /// Groups the items emitted by the source Observable by the key returned by
+  /// `discr`, as `group_by`, and emits `element(item)` in the groups instead
+  /// of the items.
+  ///
+  /// ```
+  /// use rxrust::prelude::*;
+  ///
+  /// observable::from_iter(["apple", "avocado", "banana"])
+  ///   .group_by_element(|s: &&str| s.chars().next(), |s: &str| s.len())
+  ///   .subscribe(|group| {
+  ///     let key = group.key;
+  ///     group.sum().subscribe(move |len| println!("{:?}: {}", key, len));
+  ///   });
+  ///
+  /// // Prints:
+  /// //  Some('a'): 12
+  /// //  Some('b'): 6
+  /// ```
+  #[inline]
+  fn group_by_element<D, S, Item, Key, Subject>(
+    self,
+    discr: D,
+    element: S,
+  ) -> GroupByOp<Self, D, S, Subject>
   where
     D: FnMut(&Item) -> Key,
   {
     GroupByOp {
       source: self,
       discr,
+      selector: element,
+      _hint: TypeHint::new(),
+    }
+  }
+
+  /// Groups the items emitted by the source Observable as
+  /// `group_by_element`, and completes each group when the observable
+  /// returned by `duration` for it emits its first item or terminates.
+  /// A later item of the same key opens a new group, so the groups of idle
+  /// keys don't stay in memory.
+  ///
+  /// ```
+  /// use rxrust::prelude::*;
+  ///
+  /// let mut source = LocalSubject::new();
+  /// source
+  ///   .clone()
+  ///   .group_by_until(
+  ///     |v: &i32| v % 2,
+  ///     |v| v,
+  ///     // A group closes after it received two items.
+  ///     |group| group.clone().skip(1),
+  ///   )
+  ///   .subscribe(|group| {
+  ///     let key = group.key;
+  ///     group
+  ///       .reduce_initial(vec![], |mut acc, v| {
+  ///         acc.push(v);
+  ///         acc
+  ///       })
+  ///       .subscribe(move |items| println!("{}: {:?}", key, items));
+  ///   });
+  ///
+  /// (0..5).for_each(|v| source.next(v));
+  /// source.complete();
+  ///
+  /// // Prints:
+  /// //  0: [0, 2]
+  /// //  1: [1, 3]
+  /// //  0: [4]
+  /// ```
+  #[inline]
+  fn group_by_until<D, S, F, Item, Key, Subject, Duration>(
+    self,
+    discr: D,
+    element: S,
+    duration: F,
+  ) -> GroupByUntilOp<Self, D, S, F, Subject>
+  where
+    D: FnMut(&Item) -> Key,
+    F: FnMut(&GroupObservable<Key, Subject>) -> Duration,
+  {
+    GroupByUntilOp {
+      source: self,
+      discr,
+      selector: element,
+      duration,
+      _hint: TypeHint::new(),
     }
   }
 
@@ -1105,26 +1223,167 @@ pub trait Observable: Sized {
   /// items when it is subscribed to, but only when the Connect operator is
   /// applied to it. In this way you can wait for all intended observers to
   /// subscribe to the Observable before the Observable begins emitting items.
+  ///
+  /// The subject is a `LocalSubject` for a local source and a `SharedSubject`
+  /// for a shared one, use `multicast` to multicast through another subject.
   #[inline]
-  fn publish<Subject: Default>(self) -> ConnectableObservable<Self, Subject> {
+  fn publish<Subject: Default>(self) -> ConnectableObservable<Self, Subject>
+  where
+    ConnectableObservable<Self, Subject>: Publish,
+  {
     ConnectableObservable::new(self)
   }
 
+  /// Returns a ConnectableObservable multicasting through a subject made by
+  /// `factory`. Unlike `publish`, every time its ref count reconnects to the
+  /// source it does so through a fresh subject made by `factory`.
+  ///
+  /// ```rust
+  /// # use rxrust::prelude::*;
+  /// let values = MutRc::own(vec![]);
+  /// let ref_count = observable::from_iter(0..2)
+  ///   .multicast(LocalSubject::new)
+  ///   .into_ref_count();
+  /// let c_values = values.clone();
+  /// ref_count
+  ///   .clone()
+  ///   .subscribe(move |v| c_values.rc_deref_mut().push(v))
+  ///   .unsubscribe();
+  /// let c_values = values.clone();
+  /// ref_count.subscribe(move |v| c_values.rc_deref_mut().push(v));
+  ///
+  /// assert_eq!(*values.rc_deref(), vec![0, 1, 0, 1]);
+  /// ```
+  #[inline]
+  fn multicast<F, Subject>(
+    self,
+    factory: F,
+  ) -> ConnectableObservable<Self, Subject, F>
+  where
+    F: FnMut() -> Subject,
+  {
+    ConnectableObservable::with_factory(self, factory)
+  }
+
+  /// Returns a ConnectableObservable multicasting through a behavior subject
+  /// starting with `initial`, so every observer receives the latest value at
+  /// once. Like `publish`, the subject lives in the context of the source.
+  ///
+  /// ```rust
+  /// # use rxrust::prelude::*;
+  /// let value = MutRc::own(-1);
+  /// let published = observable::of(1)
+  ///   .publish_behavior::<LocalSubject<'_, _, _>>(0);
+  /// let c_value = value.clone();
+  /// published
+  ///   .fork()
+  ///   .subscribe(move |v| *c_value.rc_deref_mut() = v);
+  /// assert_eq!(*value.rc_deref(), 0);
+  ///
+  /// published.connect();
+  /// assert_eq!(*value.rc_deref(), 1);
+  /// ```
+  #[inline]
+  fn publish_behavior<Subject>(
+    self,
+    initial: Self::Item,
+  ) -> ConnectableObservable<Self, PublishBehaviorSubject<Self, Subject>>
+  where
+    Subject: SubjectLike<Self> + Default,
+    ConnectableObservable<Self, Subject>: Publish,
+  {
+    let subject = BehaviorSubject {
+      subject: Subject::default(),
+      value: Subject::own(initial),
+    };
+    ConnectableObservable::with_subject(self, subject)
+  }
+
+  /// Returns a ConnectableObservable multicasting through an async subject,
+  /// so observers receive only the last value, once the source completes.
+  /// Like `publish`, the subject lives in the context of the source.
+  ///
+  /// ```rust
+  /// # use rxrust::prelude::*;
+  /// let mut values = vec![];
+  /// let published = observable::from_iter(0..3)
+  ///   .publish_last::<LocalSubject<'_, _, _>>();
+  /// published.fork().subscribe(|v| values.push(v));
+  /// published.connect();
+  ///
+  /// assert_eq!(values, vec![2]);
+  /// ```
+  #[inline]
+  fn publish_last<Subject>(
+    self,
+  ) -> ConnectableObservable<Self, PublishLastSubject<Self, Subject>>
+  where
+    Subject: SubjectLike<Self> + Default,
+    ConnectableObservable<Self, Subject>: Publish,
+  {
+    let subject = AsyncSubject {
+      subject: Subject::default(),
+      state: Subject::own(AsyncState::default()),
+    };
+    ConnectableObservable::with_subject(self, subject)
+  }
+
   /// Returns a new Observable that multicast (shares) the original
   /// Observable. As long as there is at least one Subscriber this
   /// Observable will be subscribed and emitting data. When all subscribers
-  /// have unsubscribed it will unsubscribe from the source Observable.
+  /// have unsubscribed it will unsubscribe from the source Observable, the
+  /// next subscriber resubscribes it through a fresh subject.
   /// Because the Observable is multicasting it makes the stream `hot`.
-  /// This is an alias for `publish().ref_count()`
+  /// This is an alias for `multicast(Subject::default).ref_count()`
   #[inline]
   fn share<Subject>(
     self,
   ) -> <ConnectableObservable<Self, Subject> as Connect>::R
   where
     Subject: Default,
-    ConnectableObservable<Self, Subject>: Connect,
+    ConnectableObservable<Self, Subject>: Connect + Publish,
   {
-    self.publish::<Subject>().into_ref_count()
+    self
+      .multicast(Subject::default as fn() -> Subject)
+      .into_ref_count()
+  }
+
+  /// Returns a new Observable that multicast (shares) the original Observable
+  /// like `share`, with `config` choosing when the connection to the source
+  /// and its subject are reset: after the source errored, after it
+  /// completed, and after the last subscriber unsubscribed, at once, never,
+  /// or after a grace period run on the scheduler of `config`. A reset
+  /// connection is made again for the next subscriber, through a fresh
+  /// subject made by the connector of `config`.
+  ///
+  /// ```rust
+  /// # use rxrust::prelude::*;
+  /// use rxrust::ops::share::ShareConfig;
+  /// use std::time::Duration;
+  /// # let mut local = futures::executor::LocalPool::new();
+  /// # let scheduler = local.spawner();
+  ///
+  /// let mut subject = LocalSubject::new();
+  /// let shared = subject.clone().share_with(ShareConfig {
+  ///   // keep the source alive while observers come and go.
+  ///   reset_on_ref_count_zero: Duration::from_millis(100).into(),
+  ///   ..ShareConfig::new(LocalSubject::new, scheduler)
+  /// });
+  ///
+  /// shared.clone().subscribe(|v: i32| println!("{}", v)).unsubscribe();
+  /// shared.subscribe(|v| println!("{}", v));
+  /// subject.next(1);
+  /// ```
+  #[inline]
+  fn share_with<F, Subject, SD>(
+    self,
+    config: ShareConfig<F, SD>,
+  ) -> ShareWithOp<Self, Subject, F, SD>
+  where
+    F: FnMut() -> Subject,
+    Subject: SubjectLike<Self>,
+  {
+    ShareOp(Subject::own(InnerShare::new(self, config)))
   }
 
   /// Delays the emission of items from the source Observable by a given timeout
@@ -1134,6 +1393,7 @@ pub trait Observable: Sized {
     DelayOp {
       source: self,
       delay: dur,
+      at: None,
       scheduler,
     }
   }
@@ -1142,7 +1402,8 @@ pub trait Observable: Sized {
   fn delay_at<SD>(self, at: Instant, scheduler: SD) -> DelayOp<Self, SD> {
     DelayOp {
       source: self,
-      delay: at.elapsed(),
+      delay: Duration::default(),
+      at: Some(at),
       scheduler,
     }
   }
@@ -1186,7 +1447,7 @@ pub trait Observable: Sized {
   /// ```
   ///
   /// The output will instead by `1(thread 1) 2(thread 1) 3(thread 1) 4(thread
-  /// 1)  5(thread 2) 6(thread 2) 7(thread 2) 8(thread 2) 9(thread id2)`.
+  /// 1) 5(thread 2) 6(thread 2) 7(thread 2) 8(thread 2) 9(thread id2)`.
   /// The reason for this is that Observable `b` emits its values directly like
   /// before, but the emissions from `a` are scheduled on a new thread because
   /// we are now using the `NewThread` Scheduler for that specific Observable.
@@ -1198,6 +1459,38 @@ pub trait Observable: Sized {
     }
   }
 
+  /// Specify the Scheduler on which the unsubscription of an Observable will
+  /// be performed.
+  ///
+  /// Unlike `subscribe_on`, the subscription and the notifications happen on
+  /// the caller's context as usual, only the call to `unsubscribe` of the
+  /// source subscription is scheduled by `scheduler`. This is useful when the
+  /// teardown logic of the source must run on a specific executor.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use rxrust::prelude::*;
+  /// use futures::executor::LocalPool;
+  ///
+  /// let mut local = LocalPool::new();
+  /// let mut subscription = observable::never()
+  ///   .finalize(|| println!("released"))
+  ///   .unsubscribe_on(local.spawner())
+  ///   .subscribe(|_| {});
+  ///
+  /// subscription.unsubscribe();
+  /// // `released` is printed when the scheduler runs the task.
+  /// local.run();
+  /// ```
+  #[inline]
+  fn unsubscribe_on<SD>(self, scheduler: SD) -> UnsubscribeOnOp<Self, SD> {
+    UnsubscribeOnOp {
+      source: self,
+      scheduler,
+    }
+  }
+
   /// Re-emits all notifications from source Observable with specified
   /// scheduler.
   ///
@@ -1260,6 +1553,53 @@ pub trait Observable: Sized {
     }
   }
 
+  /// Attaches a timestamp to each item emitted by the source Observable, the
+  /// time is read from `scheduler`, so a virtual-time scheduler applies.
+  ///
+  /// # Example
+  /// ```
+  /// use rxrust::prelude::*;
+  /// use futures::executor::LocalPool;
+  ///
+  /// let local = LocalPool::new();
+  /// observable::from_iter(0..3)
+  ///   .timestamp(local.spawner())
+  ///   .subscribe(|(at, v)| println!("{} at {:?}", v, at));
+  /// ```
+  #[inline]
+  fn timestamp<SD>(self, scheduler: SD) -> TimestampOp<Self, SD> {
+    TimestampOp {
+      source: self,
+      scheduler,
+    }
+  }
+
+  /// Emits each item of the source Observable together with the amount of
+  /// time elapsed since the previous item, or since the subscription for the
+  /// first one. The time is read from `scheduler`.
+  ///
+  /// # Example
+  /// ```
+  /// use rxrust::prelude::*;
+  /// use futures::executor::LocalPool;
+  /// use std::time::Duration;
+  ///
+  /// let mut local = LocalPool::new();
+  /// observable::interval(Duration::from_millis(10), local.spawner())
+  ///   .take(3)
+  ///   .time_interval(local.spawner())
+  ///   .subscribe(|(elapsed, v)| println!("{} after {:?}", v, elapsed));
+  ///
+  /// local.run();
+  /// ```
+  #[inline]
+  fn time_interval<SD>(self, scheduler: SD) -> TimeIntervalOp<Self, SD> {
+    TimeIntervalOp {
+      source: self,
+      scheduler,
+    }
+  }
+
   /// Returns an Observable that emits all items emitted by the source
   /// Observable that are distinct by comparison from previous items.
   #[inline]
@@ -1534,6 +1874,58 @@ pub trait Observable: Sized {
       func: f,
     }
   }
+
+  /// Represents all of the notifications from the source Observable as `next`
+  /// emissions marked with their original types within `Notification`
+  /// objects.
+  ///
+  /// The returned Observable never errors, an error of the source is emitted
+  /// as `Notification::Error` and followed by the completion.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use rxrust::prelude::*;
+  ///
+  /// let mut notifications = vec![];
+  /// observable::of(1)
+  ///   .materialize()
+  ///   .subscribe(|n| notifications.push(n));
+  ///
+  /// assert_eq!(
+  ///   notifications,
+  ///   vec![Notification::Next(1), Notification::Complete]
+  /// );
+  /// ```
+  #[inline]
+  fn materialize(self) -> MaterializeOp<Self> { MaterializeOp { source: self } }
+
+  /// Converts an Observable of `Notification` objects into the emissions that
+  /// they represent. It's the reverse of `materialize`.
+  ///
+  /// # Example
+  ///
+  /// ```
+  /// use rxrust::prelude::*;
+  ///
+  /// observable::from_iter(vec![
+  ///   Notification::Next(1),
+  ///   Notification::Error("oops"),
+  /// ])
+  /// .dematerialize()
+  /// .subscribe_err(|v| println!("{}", v), |e| println!("error: {}", e));
+  ///
+  /// // print log:
+  /// // 1
+  /// // error: oops
+  /// ```
+  #[inline]
+  fn dematerialize<Item, Err>(self) -> DematerializeOp<Self>
+  where
+    Self: Observable<Item = Notification<Item, Err>, Err = ()>,
+  {
+    DematerializeOp { source: self }
+  }
 }
 
 pub trait LocalObservable<'a>: Observable {
@@ -1,18 +1,24 @@
 use crate::{
   impl_local_shared_both,
   prelude::*,
-  subject::{LocalSubject, SharedSubject},
+  subject::{LocalSubject, SharedSubject, SubjectLike},
 };
+use ops::auto_connect::{AutoConnectOp, InnerAutoConnect};
 use ops::ref_count::{InnerRefCount, RefCount};
 
-pub struct ConnectableObservable<Src, Sbj> {
-  source: Src,
-  subject: Sbj,
+/// An observable multicasting its source through a subject, which starts
+/// emitting only when it is connected.
+///
+/// `F` makes the fresh subject of each reconnection of
+/// [`into_ref_count`](Connect::into_ref_count), a connectable observable
+/// built without one reconnects the same subject.
+pub struct ConnectableObservable<Src, Sbj, F = fn() -> Sbj> {
+  pub(crate) source: Src,
+  pub(crate) subject: Sbj,
+  pub(crate) factory: Option<F>,
 }
 
-impl<Src, Sbj> ConnectableObservable<Src, Sbj> {}
-
-impl<Src, Sbj> Observable for ConnectableObservable<Src, Sbj>
+impl<Src, Sbj, F> Observable for ConnectableObservable<Src, Sbj, F>
 where
   Sbj: Observable,
 {
@@ -21,7 +27,7 @@ where
 }
 
 impl_local_shared_both! {
-  impl<Src, Sbj> ConnectableObservable<Src, Sbj>;
+  impl<Src, Sbj, F> ConnectableObservable<Src, Sbj, F>;
   type Unsub = Sbj::Unsub;
   macro method($self: ident, $observer: ident, $ctx: ident) {
     $self.subject.actual_subscribe($observer)
@@ -36,9 +42,33 @@ impl<Src, Sbj> ConnectableObservable<Src, Sbj> {
   where
     Sbj: Default,
   {
+    Self::with_subject(source, <_>::default())
+  }
+
+  /// Creates a connectable observable multicasting `source` through
+  /// `subject`.
+  #[inline]
+  pub fn with_subject(source: Src, subject: Sbj) -> Self {
     ConnectableObservable {
       source,
-      subject: <_>::default(),
+      subject,
+      factory: None,
+    }
+  }
+}
+
+impl<Src, Sbj, F> ConnectableObservable<Src, Sbj, F> {
+  /// Creates a connectable observable multicasting `source` through a subject
+  /// made by `factory`, and through a fresh one on each reconnection.
+  #[inline]
+  pub fn with_factory(source: Src, mut factory: F) -> Self
+  where
+    F: FnMut() -> Sbj,
+  {
+    ConnectableObservable {
+      source,
+      subject: factory(),
+      factory: Some(factory),
     }
   }
 
@@ -49,89 +79,135 @@ impl<Src, Sbj> ConnectableObservable<Src, Sbj> {
   {
     self.subject.clone()
   }
+
+  /// Returns an observable connecting this one when the `n`th observer
+  /// subscribes to it, or at once if `n` is zero, and handing the
+  /// connection to `on_connect`. Unsubscribing the observers never closes
+  /// the connection, `on_connect` owns it.
+  ///
+  /// ```rust
+  /// # use rxrust::prelude::*;
+  /// let mut subject = LocalSubject::new();
+  /// let mut connection = None;
+  /// let auto = subject
+  ///   .clone()
+  ///   .publish()
+  ///   .auto_connect(2, |c| connection = Some(c));
+  ///
+  /// auto.clone().subscribe(|v: i32| println!("first {}", v));
+  /// // not connected yet.
+  /// subject.next(1);
+  /// auto.subscribe(|v| println!("second {}", v));
+  /// subject.next(2);
+  ///
+  /// connection.unwrap().unsubscribe();
+  /// ```
+  pub fn auto_connect<C>(
+    self,
+    n: usize,
+    on_connect: C,
+  ) -> AutoConnect<Src, Sbj, F, C>
+  where
+    Sbj: SubjectLike<Src>,
+    C: FnOnce(Sbj::Connection),
+  {
+    AutoConnectOp(Sbj::own(InnerAutoConnect::new(self, n, on_connect)))
+  }
+
+  /// Replaces the subject with a fresh one, if there is a factory.
+  pub(crate) fn renew(&mut self)
+  where
+    F: FnMut() -> Sbj,
+  {
+    if let Some(factory) = &mut self.factory {
+      self.subject = factory();
+    }
+  }
 }
 
 impl<'a, Src, Item, Err>
   ConnectableObservable<Src, LocalSubject<'a, Item, Err>>
 {
   #[inline]
-  pub fn local(source: Src) -> Self {
-    ConnectableObservable {
-      source,
-      subject: <_>::default(),
-    }
-  }
+  pub fn local(source: Src) -> Self { Self::new(source) }
 }
 
 impl<Src, Item, Err> ConnectableObservable<Src, SharedSubject<Item, Err>> {
   #[inline]
-  pub fn shared(source: Src) -> Self {
-    ConnectableObservable {
-      source,
-      subject: <_>::default(),
-    }
-  }
+  pub fn shared(source: Src) -> Self { Self::new(source) }
 }
 
-pub trait Connect {
-  type R;
-  type Unsub;
-  fn into_ref_count(self) -> Self::R;
-  fn connect(self) -> Self::Unsub;
-}
+/// Implemented by the connectable observables multicasting their source
+/// through the subject of its context, a [`LocalSubject`] for a local source
+/// and a [`SharedSubject`] for a shared one, so `publish` can infer its
+/// subject.
+pub trait Publish {}
 
-type LocalInnerRefCount<'a, Src> = MutRc<
-  InnerRefCount<
-    Src,
-    LocalSubject<'a, <Src as Observable>::Item, <Src as Observable>::Err>,
-    <Src as LocalObservable<'a>>::Unsub,
-  >,
->;
-impl<'a, Src> Connect
+impl<'a, Src> Publish
   for ConnectableObservable<Src, LocalSubject<'a, Src::Item, Src::Err>>
 where
   Src: LocalObservable<'a>,
-  Src::Item: Clone + 'a,
-  Src::Err: Clone + 'a,
 {
-  type R = RefCount<LocalInnerRefCount<'a, Src>>;
-  type Unsub = Src::Unsub;
-
-  #[inline]
-  fn into_ref_count(self) -> Self::R { RefCount::local(self) }
+}
 
-  #[inline]
-  fn connect(self) -> Self::Unsub { self.source.actual_subscribe(self.subject) }
+impl<Src> Publish
+  for ConnectableObservable<Src, SharedSubject<Src::Item, Src::Err>>
+where
+  Src: SharedObservable,
+{
 }
 
-type SharedInnerRefCount<Src> = MutArc<
-  InnerRefCount<
+/// The pointer the operators multicasting `Src` through `Sbj` share their
+/// state with.
+type SubjectRc<Src, Sbj, T> = <Sbj as SubjectLike<Src>>::Rc<T>;
+
+/// The async subject `publish_last` multicasts `Src` through, in the context
+/// of the subject `Sbj`.
+pub(crate) type PublishLastSubject<Src, Sbj> = AsyncSubject<
+  Sbj,
+  SubjectRc<
     Src,
-    SharedSubject<<Src as Observable>::Item, <Src as Observable>::Err>,
-    <Src as SharedObservable>::Unsub,
+    Sbj,
+    AsyncState<<Src as Observable>::Item, <Src as Observable>::Err>,
   >,
 >;
 
-impl<Src> Connect
-  for ConnectableObservable<Src, SharedSubject<Src::Item, Src::Err>>
+/// The behavior subject `publish_behavior` multicasts `Src` through, in the
+/// context of the subject `Sbj`.
+pub(crate) type PublishBehaviorSubject<Src, Sbj> =
+  BehaviorSubject<Sbj, SubjectRc<Src, Sbj, <Src as Observable>::Item>>;
+
+type AutoConnect<Src, Sbj, F, C> =
+  AutoConnectOp<SubjectRc<Src, Sbj, InnerAutoConnect<Src, Sbj, F, C>>>;
+
+pub trait Connect {
+  type R;
+  type Unsub;
+  fn into_ref_count(self) -> Self::R;
+  fn connect(self) -> Self::Unsub;
+}
+
+impl<Src, Sbj, F> Connect for ConnectableObservable<Src, Sbj, F>
 where
-  Src: SharedObservable,
-  Src::Item: Clone + Send + Sync + 'static,
-  Src::Err: Clone + Send + Sync + 'static,
+  Sbj: SubjectLike<Src>,
 {
-  type R = RefCount<SharedInnerRefCount<Src>>;
-  type Unsub = Src::Unsub;
+  type R =
+    RefCount<SubjectRc<Src, Sbj, InnerRefCount<Src, Sbj, F, Sbj::Connection>>>;
+  type Unsub = Sbj::Connection;
 
   #[inline]
-  fn into_ref_count(self) -> Self::R { RefCount::shared(self) }
+  fn into_ref_count(self) -> Self::R {
+    RefCount(Sbj::own(InnerRefCount::new(self)))
+  }
 
   #[inline]
-  fn connect(self) -> Self::Unsub { self.source.actual_subscribe(self.subject) }
+  fn connect(self) -> Self::Unsub { self.subject.subscribe_to(self.source) }
 }
 
 #[cfg(test)]
 mod test {
   use super::*;
+  use crate::test_observer::TestObserver;
 
   #[test]
   fn smoke() {
@@ -170,6 +246,48 @@ mod test {
     assert_eq!(second, 100);
   }
 
+  #[test]
+  fn publish_behavior() {
+    let mut values = vec![];
+    {
+      let p = observable::from_iter(1..3)
+        .publish_behavior::<LocalSubject<'_, _, _>>(0);
+      p.fork().subscribe(|v| values.push(v));
+      p.connect();
+    }
+
+    assert_eq!(values, vec![0, 1, 2]);
+  }
+
+  #[test]
+  fn publish_last() {
+    let observer = TestObserver::new();
+    let late = TestObserver::new();
+    let p =
+      observable::from_iter(1..4).publish_last::<LocalSubject<'_, _, _>>();
+    let subject = p.fork();
+    subject.clone().actual_subscribe(observer.clone());
+    p.connect();
+    subject.actual_subscribe(late.clone());
+
+    observer.assert_values(&[3]).assert_complete();
+    late.assert_values(&[3]).assert_complete();
+  }
+
+  #[cfg(not(target_arch = "wasm32"))]
+  #[test]
+  fn publish_behavior_shared() {
+    let value = MutArc::own(-1);
+    let c_value = value.clone();
+    let p = observable::of(1).into_shared().publish_behavior(0);
+    p.fork()
+      .into_shared()
+      .subscribe(move |v| *c_value.rc_deref_mut() = v);
+    assert_eq!(*value.rc_deref(), 0);
+    p.connect();
+    assert_eq!(*value.rc_deref(), 1);
+  }
+
   #[test]
   fn bench() { do_bench(); }
 
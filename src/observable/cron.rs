@@ -0,0 +1,583 @@
+use crate::scheduler::Instant;
+use crate::{impl_helper::*, impl_local_shared_both, prelude::*};
+use std::fmt;
+use std::str::FromStr;
+// `std::time::SystemTime::now` panics on wasm32, the timer crate reexports
+// the std types elsewhere.
+use fluvio_wasm_timer::{SystemTime, UNIX_EPOCH};
+use std::time::Duration;
+
+/// Creates an observable which emits the scheduled `SystemTime` at every
+/// instant matching the cron expression `expr`, and completes if no instant
+/// matches anymore.
+///
+/// `expr` is a standard cron expression of five fields, `minute hour
+/// day-of-month month day-of-week`, or six fields with a leading `second`.
+/// See [`CronSchedule`] for the syntax. Times are matched in UTC.
+///
+/// The times are `std::time::SystemTime`, except on wasm32 where they are
+/// the `SystemTime` of `fluvio_wasm_timer`, which can read the clock there.
+///
+/// Every instant is scheduled on `scheduler` with a delay recomputed from
+/// `scheduler.now()`, so the observable can be driven by a virtual clock.
+///
+/// ```rust
+/// # use rxrust::prelude::*;
+/// use futures::executor::ThreadPool;
+///
+/// let pool = ThreadPool::new().unwrap();
+/// // Every day at 03:30.
+/// let mut subscription = observable::cron("30 3 * * *", pool)
+///   .unwrap()
+///   .into_shared()
+///   .subscribe(|at| println!("compaction scheduled at {:?}", at));
+/// subscription.unsubscribe();
+/// ```
+pub fn cron<S>(
+  expr: &str,
+  scheduler: S,
+) -> Result<CronObservable<S>, CronError> {
+  Ok(CronObservable {
+    schedule: expr.parse()?,
+    scheduler,
+  })
+}
+
+#[derive(Clone)]
+pub struct CronObservable<S> {
+  schedule: CronSchedule,
+  scheduler: S,
+}
+
+impl<S> Observable for CronObservable<S> {
+  type Item = SystemTime;
+  type Err = ();
+}
+
+impl_local_shared_both! {
+  impl<S> CronObservable<S>;
+  type Unsub = @ctx::Rc<CronSubscription>;
+  macro method($self:ident, $observer: ident, $ctx: ident) {
+    let subscription = $ctx::Rc::own(CronSubscription::default());
+    let started = SystemTime::now();
+    CronTask {
+      observer: $observer,
+      schedule: $self.schedule,
+      clock: ($self.scheduler.now(), started),
+      scheduler: $ctx::Rc::own($self.scheduler),
+      last: started,
+      subscription: subscription.clone(),
+    }
+    .schedule_next();
+    subscription
+  }
+  where
+    @ctx::local_only('o: 'static,)
+    S: @ctx::Scheduler + @ctx::shared_only(Send + Sync +) 'static
+}
+
+/// The subscription of [`cron`], cancels the instant currently scheduled.
+#[derive(Default)]
+pub struct CronSubscription {
+  closed: bool,
+  handle: Option<SpawnHandle>,
+}
+
+impl SubscriptionLike for CronSubscription {
+  fn unsubscribe(&mut self) {
+    self.closed = true;
+    if let Some(mut handle) = self.handle.take() {
+      handle.unsubscribe();
+    }
+  }
+
+  #[inline]
+  fn is_closed(&self) -> bool { self.closed }
+}
+
+struct CronTask<O, S, U> {
+  observer: O,
+  schedule: CronSchedule,
+  scheduler: S,
+  /// The time of the scheduler and the wall clock time at subscription, to
+  /// convert the time of the scheduler to a `SystemTime`.
+  clock: (Instant, SystemTime),
+  last: SystemTime,
+  subscription: U,
+}
+
+trait ScheduleNext {
+  fn schedule_next(self);
+}
+
+macro_rules! schedule_next {
+  ($task: ident) => {{
+    let mut task = $task;
+    let Some(next) = task.schedule.next_after(task.last) else {
+      task.observer.complete();
+      return;
+    };
+    let (started, wall_started) = task.clock;
+    let now = wall_started + (task.scheduler.rc_deref().now() - started);
+    let delay = next.duration_since(now).unwrap_or_default();
+    let subscription = task.subscription.clone();
+    let scheduler = task.scheduler.clone();
+    let handle = scheduler.rc_deref().schedule(
+      move |mut task: Self| {
+        if !task.subscription.is_closed() {
+          task.observer.next(next);
+          task.last = next;
+          task.schedule_next();
+        }
+      },
+      Some(delay),
+      task,
+    );
+    // A synchronous scheduler already ran the task and scheduled the next.
+    if !handle.is_closed() {
+      let mut subscription = subscription.rc_deref_mut();
+      if subscription.closed {
+        let mut handle = handle;
+        handle.unsubscribe();
+      } else {
+        subscription.handle = Some(handle);
+      }
+    }
+  }};
+}
+
+impl<O, S> ScheduleNext for CronTask<O, MutRc<S>, MutRc<CronSubscription>>
+where
+  O: Observer<Item = SystemTime, Err = ()> + 'static,
+  S: LocalScheduler + 'static,
+{
+  fn schedule_next(self) { schedule_next!(self) }
+}
+
+#[cfg(not(all(target_arch = "wasm32")))]
+impl<O, S> ScheduleNext for CronTask<O, MutArc<S>, MutArc<CronSubscription>>
+where
+  O: Observer<Item = SystemTime, Err = ()> + Send + 'static,
+  S: SharedScheduler + Send + Sync + 'static,
+{
+  fn schedule_next(self) { schedule_next!(self) }
+}
+
+/// A parsed cron expression, see [`cron`].
+///
+/// Each field is `*`, a value, a range `a-b`, or a comma separated list of
+/// them, and a value or a range can be followed by a step `/n`. Months can be
+/// given by `JAN`-`DEC` and days of week by `SUN`-`SAT`, where both `0` and
+/// `7` are Sunday. `?` is the same as `*` in the day fields. As in standard
+/// cron, if both the day of month and the day of week are restricted, a day
+/// matching either of them matches.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CronSchedule {
+  seconds: u64,
+  minutes: u64,
+  hours: u64,
+  days_of_month: u64,
+  months: u64,
+  days_of_week: u64,
+  any_day_of_month: bool,
+  any_day_of_week: bool,
+}
+
+/// The error of parsing a cron expression.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CronError {
+  /// The expression does not have five or six fields.
+  FieldCount(usize),
+  /// A field is not valid.
+  InvalidField { field: &'static str, value: String },
+}
+
+impl fmt::Display for CronError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      CronError::FieldCount(count) => {
+        write!(f, "expected 5 or 6 cron fields, found {}", count)
+      }
+      CronError::InvalidField { field, value } => {
+        write!(f, "invalid cron {} field `{}`", field, value)
+      }
+    }
+  }
+}
+
+impl std::error::Error for CronError {}
+
+const MONTHS: [&str; 12] = [
+  "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV",
+  "DEC",
+];
+const DAYS_OF_WEEK: [&str; 7] =
+  ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+impl FromStr for CronSchedule {
+  type Err = CronError;
+
+  fn from_str(expr: &str) -> Result<Self, CronError> {
+    let fields: Vec<_> = expr.split_whitespace().collect();
+    let (second, rest) = match fields.len() {
+      5 => ("0", &fields[..]),
+      6 => (fields[0], &fields[1..]),
+      count => return Err(CronError::FieldCount(count)),
+    };
+    let any_day = |f: &str| f.starts_with('*') || f == "?";
+    let mut days_of_week =
+      parse_field("day of week", rest[4], 0, 7, &DAYS_OF_WEEK)?;
+    // Both 0 and 7 are Sunday.
+    if days_of_week & 1 << 7 != 0 {
+      days_of_week = (days_of_week | 1) & !(1 << 7);
+    }
+    Ok(CronSchedule {
+      seconds: parse_field("second", second, 0, 59, &[])?,
+      minutes: parse_field("minute", rest[0], 0, 59, &[])?,
+      hours: parse_field("hour", rest[1], 0, 23, &[])?,
+      days_of_month: parse_field("day of month", rest[2], 1, 31, &[])?,
+      months: parse_field("month", rest[3], 1, 12, &MONTHS)?,
+      days_of_week,
+      any_day_of_month: any_day(rest[2]),
+      any_day_of_week: any_day(rest[4]),
+    })
+  }
+}
+
+/// Parses a field to a bit set of the values it matches. `names` are the
+/// aliases of the values from `min`.
+fn parse_field(
+  field: &'static str,
+  value: &str,
+  min: u32,
+  max: u32,
+  names: &[&str],
+) -> Result<u64, CronError> {
+  let invalid = || CronError::InvalidField {
+    field,
+    value: value.to_string(),
+  };
+  let number = |s: &str| {
+    names
+      .iter()
+      .position(|n| n.eq_ignore_ascii_case(s))
+      .map(|i| i as u32 + min)
+      .or_else(|| s.parse().ok())
+      .filter(|v| (min..=max).contains(v))
+      .ok_or_else(invalid)
+  };
+
+  let mut bits = 0;
+  for part in value.split(',') {
+    let (range, step) = match part.split_once('/') {
+      Some((range, step)) => {
+        let step: u32 = step.parse().map_err(|_| invalid())?;
+        if step == 0 {
+          return Err(invalid());
+        }
+        (range, step)
+      }
+      None => (part, 1),
+    };
+    let (start, end) = match range {
+      "*" | "?" => (min, max),
+      _ => match range.split_once('-') {
+        Some((start, end)) => (number(start)?, number(end)?),
+        // A single value with a step runs to the end of the field.
+        None if step > 1 => (number(range)?, max),
+        None => {
+          let v = number(range)?;
+          (v, v)
+        }
+      },
+    };
+    if start > end {
+      return Err(invalid());
+    }
+    bits |= (start..=end)
+      .step_by(step as usize)
+      .fold(0, |b, v| b | 1 << v);
+  }
+  Ok(bits)
+}
+
+impl CronSchedule {
+  /// Returns the first instant matching the schedule strictly after `time`,
+  /// or `None` if none matches in the next years.
+  pub fn next_after(&self, time: SystemTime) -> Option<SystemTime> {
+    let secs = time.duration_since(UNIX_EPOCH).ok()?.as_secs() + 1;
+    let start = DateTime::from_secs(secs);
+    // Search field by field, only the fields at the start are bounded by it.
+    for year in start.year..=start.year + 8 {
+      let this_year = year == start.year;
+      let first_month = if this_year { start.month } else { 1 };
+      for month in first_month..=12 {
+        if !self.matches(self.months, month) {
+          continue;
+        }
+        let this_month = this_year && month == start.month;
+        let first_day = if this_month { start.day } else { 1 };
+        let first_of_month = days_from_civil(year, month, 1);
+        let month_len = days_in_month(year, month);
+        for day in first_day..=month_len {
+          let days = first_of_month + i64::from(day - 1);
+          if !self.day_matches(day, weekday(days)) {
+            continue;
+          }
+          let today = this_month && day == start.day;
+          let from = if today { start.second_of_day } else { 0 };
+          if let Some(second) = self.second_of_day(from) {
+            return Some(
+              UNIX_EPOCH
+                + Duration::from_secs(days as u64 * 86400 + u64::from(second)),
+            );
+          }
+        }
+      }
+    }
+    None
+  }
+
+  /// Returns the first second of a day matching the time fields, from the
+  /// second `from` of the day.
+  fn second_of_day(&self, from: u32) -> Option<u32> {
+    let (from_hour, from_minute) = (from / 3600, from / 60 % 60);
+    for hour in from_hour..24 {
+      if !self.matches(self.hours, hour) {
+        continue;
+      }
+      let first_minute = if hour == from_hour { from_minute } else { 0 };
+      for minute in first_minute..60 {
+        if !self.matches(self.minutes, minute) {
+          continue;
+        }
+        let first_second = if hour == from_hour && minute == from_minute {
+          from % 60
+        } else {
+          0
+        };
+        if let Some(second) =
+          (first_second..60).find(|s| self.matches(self.seconds, *s))
+        {
+          return Some(hour * 3600 + minute * 60 + second);
+        }
+      }
+    }
+    None
+  }
+
+  #[inline]
+  fn matches(&self, bits: u64, value: u32) -> bool { bits & 1 << value != 0 }
+
+  fn day_matches(&self, day: u32, weekday: u32) -> bool {
+    let dom = self.matches(self.days_of_month, day);
+    let dow = self.matches(self.days_of_week, weekday);
+    match (self.any_day_of_month, self.any_day_of_week) {
+      (false, false) => dom || dow,
+      _ => dom && dow,
+    }
+  }
+}
+
+/// A UTC date, and the second of the day.
+struct DateTime {
+  year: i64,
+  month: u32,
+  day: u32,
+  second_of_day: u32,
+}
+
+impl DateTime {
+  fn from_secs(secs: u64) -> Self {
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    DateTime {
+      year,
+      month,
+      day,
+      second_of_day: (secs % 86400) as u32,
+    }
+  }
+}
+
+/// Sunday is 0, the epoch is a Thursday.
+fn weekday(days: i64) -> u32 { (days + 4).rem_euclid(7) as u32 }
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+  let (next_year, next_month) = if month == 12 {
+    (year + 1, 1)
+  } else {
+    (year, month + 1)
+  };
+  (days_from_civil(next_year, next_month, 1) - days_from_civil(year, month, 1))
+    as u32
+}
+
+// The conversions between days since the epoch and the civil date are the
+// algorithms of http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+  let y = if month <= 2 { year - 1 } else { year };
+  let era = y.div_euclid(400);
+  let yoe = y - era * 400;
+  let m = i64::from(month);
+  let doy =
+    (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + i64::from(day) - 1;
+  let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+  era * 146097 + doe - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+  let z = days + 719468;
+  let era = z.div_euclid(146097);
+  let doe = z - era * 146097;
+  let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+  let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+  let mp = (5 * doy + 2) / 153;
+  let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+  let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+  let year = yoe + era * 400 + i64::from(month <= 2);
+  (year, month, day)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::test_scheduler::TestScheduler;
+
+  // 2021-01-01T00:00:00Z, a Friday.
+  const NEW_YEAR_2021: u64 = 1609459200;
+
+  fn at(secs_after_new_year: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(NEW_YEAR_2021 + secs_after_new_year)
+  }
+
+  fn next(expr: &str, after: SystemTime) -> Option<SystemTime> {
+    expr.parse::<CronSchedule>().unwrap().next_after(after)
+  }
+
+  #[test]
+  fn calendar() {
+    for days in [-719468, -1, 0, 1, 18628, 19000, 100000] {
+      let (y, m, d) = civil_from_days(days);
+      assert_eq!(days_from_civil(y, m, d), days);
+    }
+    assert_eq!(civil_from_days(18628), (2021, 1, 1));
+    assert_eq!(weekday(18628), 5);
+    assert_eq!(days_in_month(2024, 2), 29);
+    assert_eq!(days_in_month(2100, 2), 28);
+    assert_eq!(days_in_month(2021, 12), 31);
+  }
+
+  #[test]
+  fn next_after() {
+    let hour = 3600;
+    let day = 24 * hour;
+    assert_eq!(next("* * * * *", at(0)), Some(at(60)));
+    assert_eq!(next("*/15 * * * * *", at(1)), Some(at(15)));
+    assert_eq!(next("30 3 * * *", at(0)), Some(at(3 * hour + 30 * 60)));
+    assert_eq!(
+      next("30 3 * * *", at(4 * hour)),
+      Some(at(day + 3 * hour + 30 * 60))
+    );
+    // The first Monday of 2021 is the 4th.
+    assert_eq!(next("0 0 * * MON", at(0)), Some(at(3 * day)));
+    assert_eq!(next("0 0 * * 1-5", at(0)), Some(at(3 * day)));
+    // The day of month or the day of week.
+    assert_eq!(next("0 0 2 * 1", at(0)), Some(at(day)));
+    assert_eq!(next("0 0 1 MAR *", at(0)), Some(at(31 * day + 28 * day)));
+    // Sunday is both 0 and 7.
+    assert_eq!(next("0 0 * * 7", at(0)), Some(at(2 * day)));
+    assert_eq!(next("0 0 29 2 *", at(0)), {
+      let leap = UNIX_EPOCH + Duration::from_secs(1709164800);
+      Some(leap)
+    });
+    assert_eq!(next("0 0 30 2 *", at(0)), None);
+    // Within the day of the start, and across the end of a year.
+    assert_eq!(next("59 59 23 * * *", at(0)), Some(at(day - 1)));
+    assert_eq!(next("0 0 1 1 *", at(0)), Some(at(365 * day)));
+    // A sparse expression, the last second of the next 29th of February.
+    assert_eq!(
+      next("59 59 23 29 2 *", at(0)),
+      Some(UNIX_EPOCH + Duration::from_secs(1709164800 + day - 1))
+    );
+  }
+
+  #[test]
+  fn parse_error() {
+    assert_eq!(
+      "* * *".parse::<CronSchedule>(),
+      Err(CronError::FieldCount(3))
+    );
+    for expr in [
+      "60 * * * *",
+      "* 5-1 * * *",
+      "* * 0 * *",
+      "* * * FOO *",
+      "*/0 * * * *",
+    ] {
+      assert!(
+        matches!(
+          expr.parse::<CronSchedule>(),
+          Err(CronError::InvalidField { .. })
+        ),
+        "{}",
+        expr
+      );
+    }
+    assert_eq!(
+      "60 * * * *"
+        .parse::<CronSchedule>()
+        .unwrap_err()
+        .to_string(),
+      "invalid cron minute field `60`"
+    );
+  }
+
+  #[test]
+  fn emit_and_unsubscribe() {
+    let scheduler = TestScheduler::default();
+    let values = MutRc::own(vec![]);
+    let c_values = values.clone();
+    let mut subscription = cron("* * * * * *", scheduler.clone())
+      .unwrap()
+      .subscribe(move |v| c_values.rc_deref_mut().push(v));
+
+    scheduler.advance_by(Duration::from_secs(3));
+    {
+      let values = values.rc_deref();
+      assert_eq!(values.len(), 3);
+      for pair in values.windows(2) {
+        assert_eq!(
+          pair[1].duration_since(pair[0]).unwrap(),
+          Duration::from_secs(1)
+        );
+      }
+      assert_eq!(
+        values[0].duration_since(UNIX_EPOCH).unwrap().subsec_nanos(),
+        0
+      );
+    }
+
+    subscription.unsubscribe();
+    scheduler.advance_by(Duration::from_secs(3));
+    assert_eq!(values.rc_deref().len(), 3);
+    assert_eq!(scheduler.pending_tasks(), 0);
+  }
+
+  #[cfg(not(target_arch = "wasm32"))]
+  #[test]
+  fn shared() {
+    use std::sync::{Arc, Mutex};
+    let scheduler = TestScheduler::default();
+    let count = Arc::new(Mutex::new(0));
+    let c_count = count.clone();
+    cron("*/10 * * * * *", scheduler.clone())
+      .unwrap()
+      .take(2)
+      .into_shared()
+      .subscribe(move |_| *c_count.lock().unwrap() += 1);
+
+    scheduler.advance_by(Duration::from_secs(60));
+    assert_eq!(*count.lock().unwrap(), 2);
+    assert_eq!(scheduler.pending_tasks(), 0);
+  }
+}
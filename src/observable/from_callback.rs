@@ -0,0 +1,423 @@
+use crate::prelude::*;
+
+/// The handler passed to the register function of a local callback
+/// observable, call it to emit a value.
+pub type LocalCallback<'a, Item> = Box<dyn FnMut(Item) + 'a>;
+
+/// The handler passed to the register function of a shared callback
+/// observable, call it to emit a value.
+pub type SharedCallback<Item> = Box<dyn FnMut(Item) + Send + Sync>;
+
+/// Creates a hot observable from a callback based API.
+///
+/// On each subscription `register` is called with a handler which emits every
+/// value it is called with. `register` returns a token, which is passed to
+/// `unregister` when the subscription is unsubscribed. The observable never
+/// completes by itself.
+///
+/// The handler is a [`LocalCallback`] for local observables and a
+/// [`SharedCallback`] for shared observables.
+///
+/// ```rust
+/// # use rxrust::prelude::*;
+/// use std::{cell::RefCell, rc::Rc};
+///
+/// let listeners: Rc<RefCell<Vec<LocalCallback<'static, i32>>>> =
+///   <_>::default();
+/// let c_listeners = listeners.clone();
+/// let mut subscription = observable::from_callback(
+///   move |cb| c_listeners.borrow_mut().push(cb),
+///   |_| println!("unregistered"),
+/// )
+/// .subscribe(|v| println!("{}", v));
+///
+/// listeners.borrow_mut().iter_mut().for_each(|cb| cb(1));
+/// subscription.unsubscribe();
+///
+/// // print log:
+/// // 1
+/// // unregistered
+/// ```
+pub fn from_callback<Item, R, U>(
+  register: R,
+  unregister: U,
+) -> CallbackObservable<R, U, Item> {
+  CallbackObservable {
+    register,
+    unregister,
+    _hint: TypeHint::new(),
+  }
+}
+
+/// Converts a function which reports its result to a completion callback into
+/// a function returning an observable.
+///
+/// Every time the returned function is called with its arguments, it returns
+/// an observable which calls `f` on subscription. The first value passed to
+/// the callback is emitted and then the observable completes, later calls of
+/// the callback are ignored, and so are the calls after unsubscribed.
+///
+/// `f` returns a token, which is passed to `cancel` when the subscription is
+/// unsubscribed before the callback was called, to cancel the pending call.
+///
+/// ```rust
+/// # use rxrust::prelude::*;
+/// fn add(args: (i32, i32), cb: impl FnOnce(i32)) { cb(args.0 + args.1) }
+///
+/// let add = observable::bind_callback(
+///   |args, cb: LocalCallback<'_, i32>| add(args, cb),
+///   |_| {},
+/// );
+/// add((1, 2)).subscribe(|v| println!("{}", v));
+///
+/// // print log:
+/// // 3
+/// ```
+pub fn bind_callback<F, C, Args, Item>(
+  f: F,
+  cancel: C,
+) -> impl Fn(Args) -> BindCallbackObservable<F, C, Args, Item>
+where
+  F: Clone,
+  C: Clone,
+{
+  move |args| BindCallbackObservable {
+    f: f.clone(),
+    cancel: cancel.clone(),
+    args,
+    _hint: TypeHint::new(),
+  }
+}
+
+#[derive(Clone)]
+pub struct CallbackObservable<R, U, Item> {
+  register: R,
+  unregister: U,
+  _hint: TypeHint<Item>,
+}
+
+impl<R, U, Item> Observable for CallbackObservable<R, U, Item> {
+  type Item = Item;
+  type Err = ();
+}
+
+macro_rules! callback_subscribe {
+  ($self: ident, $observer: ident, $rc: ident) => {{
+    let subscription = $rc::own(SingleSubscription::default());
+    let c_subscription = subscription.clone();
+    let mut observer = $observer;
+    let token = ($self.register)(Box::new(move |v| {
+      if !c_subscription.is_closed() {
+        observer.next(v);
+      }
+    }));
+    CallbackSubscription {
+      teardown: Some(($self.unregister, token)),
+      subscription,
+    }
+  }};
+}
+
+impl<'o, R, U, T, Item> LocalObservable<'o> for CallbackObservable<R, U, Item>
+where
+  R: FnOnce(LocalCallback<'o, Item>) -> T,
+  U: FnOnce(T),
+{
+  type Unsub = CallbackSubscription<U, T, MutRc<SingleSubscription>>;
+
+  fn actual_subscribe<O>(self, observer: O) -> Self::Unsub
+  where
+    O: Observer<Item = Self::Item, Err = Self::Err> + 'o,
+  {
+    callback_subscribe!(self, observer, MutRc)
+  }
+}
+
+#[cfg(not(all(target_arch = "wasm32")))]
+impl<R, U, T, Item> SharedObservable for CallbackObservable<R, U, Item>
+where
+  R: FnOnce(SharedCallback<Item>) -> T,
+  U: FnOnce(T) + Send + Sync + 'static,
+  T: Send + Sync + 'static,
+{
+  type Unsub = CallbackSubscription<U, T, MutArc<SingleSubscription>>;
+
+  fn actual_subscribe<O>(self, observer: O) -> Self::Unsub
+  where
+    O: Observer<Item = Self::Item, Err = Self::Err> + Sync + Send + 'static,
+  {
+    callback_subscribe!(self, observer, MutArc)
+  }
+}
+
+/// The subscription of [`from_callback`] and [`bind_callback`], call the
+/// `unregister` or `cancel` function with the token when unsubscribed, unless
+/// the callback already finished.
+pub struct CallbackSubscription<U, T, S> {
+  teardown: Option<(U, T)>,
+  subscription: S,
+}
+
+impl<U, T, S> SubscriptionLike for CallbackSubscription<U, T, S>
+where
+  U: FnOnce(T),
+  S: SubscriptionLike,
+{
+  fn unsubscribe(&mut self) {
+    if let Some((teardown, token)) = self.teardown.take() {
+      if !self.subscription.is_closed() {
+        self.subscription.unsubscribe();
+        teardown(token)
+      }
+    }
+  }
+
+  #[inline]
+  fn is_closed(&self) -> bool { self.subscription.is_closed() }
+}
+
+#[derive(Clone)]
+pub struct BindCallbackObservable<F, C, Args, Item> {
+  f: F,
+  cancel: C,
+  args: Args,
+  _hint: TypeHint<Item>,
+}
+
+impl<F, C, Args, Item> Observable for BindCallbackObservable<F, C, Args, Item> {
+  type Item = Item;
+  type Err = ();
+}
+
+macro_rules! bind_callback_subscribe {
+  ($self: ident, $observer: ident, $rc: ident) => {{
+    let subscription = $rc::own(SingleSubscription::default());
+    let mut c_subscription = subscription.clone();
+    let mut observer = Some($observer);
+    let token = ($self.f)(
+      $self.args,
+      Box::new(move |v| {
+        if let Some(mut observer) = observer.take() {
+          if !c_subscription.is_closed() {
+            observer.next(v);
+            observer.complete();
+            c_subscription.unsubscribe();
+          }
+        }
+      }),
+    );
+    CallbackSubscription {
+      teardown: Some(($self.cancel, token)),
+      subscription,
+    }
+  }};
+}
+
+impl<'o, F, C, T, Args, Item> LocalObservable<'o>
+  for BindCallbackObservable<F, C, Args, Item>
+where
+  F: FnOnce(Args, LocalCallback<'o, Item>) -> T,
+  C: FnOnce(T),
+{
+  type Unsub = CallbackSubscription<C, T, MutRc<SingleSubscription>>;
+
+  fn actual_subscribe<O>(self, observer: O) -> Self::Unsub
+  where
+    O: Observer<Item = Self::Item, Err = Self::Err> + 'o,
+  {
+    bind_callback_subscribe!(self, observer, MutRc)
+  }
+}
+
+#[cfg(not(all(target_arch = "wasm32")))]
+impl<F, C, T, Args, Item> SharedObservable
+  for BindCallbackObservable<F, C, Args, Item>
+where
+  F: FnOnce(Args, SharedCallback<Item>) -> T,
+  C: FnOnce(T) + Send + Sync + 'static,
+  T: Send + Sync + 'static,
+{
+  type Unsub = CallbackSubscription<C, T, MutArc<SingleSubscription>>;
+
+  fn actual_subscribe<O>(self, observer: O) -> Self::Unsub
+  where
+    O: Observer<Item = Self::Item, Err = Self::Err> + Sync + Send + 'static,
+  {
+    bind_callback_subscribe!(self, observer, MutArc)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use crate::prelude::*;
+  use std::cell::RefCell;
+  use std::rc::Rc;
+
+  type Listeners = Vec<(usize, LocalCallback<'static, i32>)>;
+
+  #[derive(Default, Clone)]
+  struct Emitter(Rc<RefCell<Listeners>>);
+
+  impl Emitter {
+    fn register(&self, cb: LocalCallback<'static, i32>) -> usize {
+      let mut listeners = self.0.borrow_mut();
+      let id = listeners.last().map_or(0, |(id, _)| id + 1);
+      listeners.push((id, cb));
+      id
+    }
+
+    fn unregister(&self, id: usize) {
+      self.0.borrow_mut().retain(|(i, _)| *i != id);
+    }
+
+    fn emit(&self, v: i32) {
+      self.0.borrow_mut().iter_mut().for_each(|(_, cb)| cb(v));
+    }
+  }
+
+  #[test]
+  fn from_callback() {
+    let emitter = Emitter::default();
+    let (e1, e2) = (emitter.clone(), emitter.clone());
+    let values = MutRc::own(vec![]);
+    let c_values = values.clone();
+
+    emitter.emit(0);
+    let mut subscription = observable::from_callback(
+      move |cb| e1.register(cb),
+      move |id| e2.unregister(id),
+    )
+    .subscribe(move |v| c_values.rc_deref_mut().push(v));
+
+    emitter.emit(1);
+    emitter.emit(2);
+    subscription.unsubscribe();
+    emitter.emit(3);
+
+    assert_eq!(&*values.rc_deref(), &[1, 2]);
+    assert!(emitter.0.borrow().is_empty());
+  }
+
+  #[test]
+  fn from_callback_after_unsubscribe() {
+    let listeners: Rc<RefCell<Vec<LocalCallback<'static, i32>>>> =
+      <_>::default();
+    let c_listeners = listeners.clone();
+    let values = MutRc::own(vec![]);
+    let c_values = values.clone();
+    let mut subscription = observable::from_callback(
+      move |cb| c_listeners.borrow_mut().push(cb),
+      |_| {},
+    )
+    .subscribe(move |v| c_values.rc_deref_mut().push(v));
+
+    listeners.borrow_mut().iter_mut().for_each(|cb| cb(1));
+    subscription.unsubscribe();
+    // The legacy API still holds the callback and calls it late.
+    listeners.borrow_mut().iter_mut().for_each(|cb| cb(2));
+
+    assert_eq!(&*values.rc_deref(), &[1]);
+    assert!(subscription.is_closed());
+  }
+
+  #[test]
+  fn from_callback_multi_subscribe() {
+    let emitter = Emitter::default();
+    let (e1, e2) = (emitter.clone(), emitter.clone());
+    let o = observable::from_callback(
+      move |cb| e1.register(cb),
+      move |id| e2.unregister(id),
+    );
+    let sum = MutRc::own(0);
+    let (c_sum1, c_sum2) = (sum.clone(), sum.clone());
+    let mut s1 = o.clone().subscribe(move |v| *c_sum1.rc_deref_mut() += v);
+    o.subscribe(move |v| *c_sum2.rc_deref_mut() += v);
+
+    emitter.emit(1);
+    s1.unsubscribe();
+    emitter.emit(10);
+
+    assert_eq!(*sum.rc_deref(), 12);
+    assert_eq!(emitter.0.borrow().len(), 1);
+  }
+
+  #[test]
+  fn bind_callback() {
+    fn add(args: (i32, i32), cb: impl FnOnce(i32)) { cb(args.0 + args.1) }
+
+    let add = observable::bind_callback(
+      |args, cb: LocalCallback<'_, i32>| add(args, cb),
+      |_| unreachable!(),
+    );
+    let mut values = vec![];
+    let mut completed = 0;
+    add((1, 2)).subscribe_complete(|v| values.push(v), || completed += 1);
+    add((3, 4)).subscribe_complete(|v| values.push(v), || completed += 1);
+
+    assert_eq!(values, vec![3, 7]);
+    assert_eq!(completed, 2);
+  }
+
+  #[test]
+  fn bind_callback_unsubscribe() {
+    let pending: Rc<RefCell<Option<LocalCallback<'static, i32>>>> =
+      <_>::default();
+    let c_pending = pending.clone();
+    let cancelled = MutRc::own(false);
+    let c_cancelled = cancelled.clone();
+    let defer = observable::bind_callback(
+      move |_: (), cb| *c_pending.borrow_mut() = Some(cb),
+      move |_| *c_cancelled.rc_deref_mut() = true,
+    );
+    let values = MutRc::own(vec![]);
+    let c_values = values.clone();
+    let mut subscription =
+      defer(()).subscribe(move |v| c_values.rc_deref_mut().push(v));
+
+    subscription.unsubscribe();
+    assert!(*cancelled.rc_deref());
+    (pending.borrow_mut().take().unwrap())(1);
+
+    assert!(values.rc_deref().is_empty());
+  }
+
+  #[cfg(not(target_arch = "wasm32"))]
+  #[test]
+  fn shared() {
+    use futures::executor::ThreadPool;
+    use std::sync::{Arc, Mutex};
+
+    let listeners: Arc<Mutex<Vec<SharedCallback<i32>>>> = <_>::default();
+    let c_listeners = listeners.clone();
+    let unregistered = Arc::new(Mutex::new(false));
+    let c_unregistered = unregistered.clone();
+    let sum = Arc::new(Mutex::new(0));
+    let c_sum = sum.clone();
+    let mut subscription = observable::from_callback(
+      move |cb| c_listeners.lock().unwrap().push(cb),
+      move |_| *c_unregistered.lock().unwrap() = true,
+    )
+    .into_shared()
+    .subscribe(move |v| *c_sum.lock().unwrap() += v);
+
+    listeners.lock().unwrap().iter_mut().for_each(|cb| cb(1));
+    subscription.unsubscribe();
+    assert!(*unregistered.lock().unwrap());
+
+    let pool = ThreadPool::new().unwrap();
+    let c_sum = sum.clone();
+    observable::bind_callback(
+      move |v: i32, cb: SharedCallback<i32>| {
+        pool.spawn_ok(async move {
+          let mut cb = cb;
+          cb(v)
+        })
+      },
+      |_| {},
+    )(2)
+    .into_shared()
+    .subscribe_blocking(move |v| *c_sum.lock().unwrap() += v);
+
+    assert_eq!(*sum.lock().unwrap(), 3);
+  }
+}
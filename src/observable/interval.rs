@@ -6,11 +6,15 @@ use std::time::Duration;
 
 /// Creates an observable which will fire at `dur` time into the future,
 /// and will repeat every `dur` interval after.
+///
+/// A late tick delays the following ones by [`IntervalPolicy::Delay`], use
+/// [`IntervalObservable::with_policy`] to change it.
 pub fn interval<S>(dur: Duration, scheduler: S) -> IntervalObservable<S> {
   IntervalObservable {
     dur,
     at: None,
     scheduler,
+    policy: IntervalPolicy::default(),
   }
 }
 
@@ -25,6 +29,7 @@ pub fn interval_at<S>(
     scheduler,
     dur,
     at: Some(at),
+    policy: IntervalPolicy::default(),
   }
 }
 
@@ -33,6 +38,29 @@ pub struct IntervalObservable<S> {
   scheduler: S,
   dur: Duration,
   at: Option<Instant>,
+  policy: IntervalPolicy,
+}
+
+impl<S> IntervalObservable<S> {
+  /// Sets how the ticks missed while the scheduler was busy are caught up.
+  ///
+  /// ```rust
+  /// # use rxrust::prelude::*;
+  /// use futures::executor::LocalPool;
+  /// use std::time::Duration;
+  ///
+  /// let mut pool = LocalPool::new();
+  /// observable::interval(Duration::from_millis(10), pool.spawner())
+  ///   .with_policy(IntervalPolicy::Skip)
+  ///   .take(3)
+  ///   .subscribe(|v| println!("{}", v));
+  /// pool.run();
+  /// ```
+  #[inline]
+  pub fn with_policy(mut self, policy: IntervalPolicy) -> Self {
+    self.policy = policy;
+    self
+  }
 }
 
 impl<S> Observable for IntervalObservable<S> {
@@ -48,6 +76,7 @@ impl_local_shared_both! {
       move |i| $observer.next(i),
       $self.dur,
       $self.at,
+      $self.policy,
     )
   }
   where
@@ -58,7 +87,7 @@ impl_local_shared_both! {
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::test_scheduler::ManualScheduler;
+  use crate::test_scheduler::TestScheduler;
   use futures::executor::LocalPool;
   #[cfg(not(target_arch = "wasm32"))]
   use futures::executor::ThreadPool;
@@ -99,7 +128,7 @@ mod tests {
 
   #[test]
   fn local_manual() {
-    let scheduler = ManualScheduler::now();
+    let scheduler = TestScheduler::default();
     let ticks = Arc::new(Mutex::new(0));
     let ticks_c = Arc::clone(&ticks);
     let delay = Duration::from_millis(1);
@@ -107,12 +136,133 @@ mod tests {
       .take(5)
       .subscribe(move |_| (*ticks_c.lock().unwrap()) += 1);
     assert_eq!(0, *ticks.lock().unwrap());
-    scheduler.advance(delay * 2);
-    scheduler.run_tasks();
+    scheduler.advance_by(delay * 2);
     assert_eq!(2, *ticks.lock().unwrap());
 
-    scheduler.advance(delay * 3);
-    scheduler.run_tasks();
+    scheduler.advance_by(delay * 3);
     assert_eq!(5, *ticks.lock().unwrap());
   }
+
+  fn late_ticks(policy: IntervalPolicy) -> Vec<Duration> {
+    let scheduler = TestScheduler::default();
+    let start = scheduler.now();
+    let ticks = Arc::new(Mutex::new(vec![]));
+    let c_ticks = ticks.clone();
+    let c_scheduler = scheduler.clone();
+    let period = Duration::from_millis(10);
+    // The first tick is 25ms late.
+    interval_at(start - Duration::from_millis(25), period, scheduler.clone())
+      .with_policy(policy)
+      .take(4)
+      .subscribe(move |_| c_ticks.lock().unwrap().push(c_scheduler.now()));
+    scheduler.advance_by(Duration::from_millis(100));
+
+    let ticks = ticks.lock().unwrap();
+    ticks.iter().map(|t| *t - start).collect()
+  }
+
+  #[test]
+  fn policy() {
+    let ms = Duration::from_millis;
+    assert_eq!(
+      late_ticks(IntervalPolicy::Burst),
+      vec![ms(0), ms(0), ms(0), ms(5)]
+    );
+    assert_eq!(
+      late_ticks(IntervalPolicy::Delay),
+      vec![ms(0), ms(10), ms(20), ms(30)]
+    );
+    assert_eq!(
+      late_ticks(IntervalPolicy::Skip),
+      vec![ms(0), ms(5), ms(15), ms(25)]
+    );
+  }
+
+  #[test]
+  fn slow_task_keeps_cadence() {
+    let ms = Duration::from_millis;
+    for policy in [
+      IntervalPolicy::Burst,
+      IntervalPolicy::Delay,
+      IntervalPolicy::Skip,
+    ] {
+      let scheduler = TestScheduler::default();
+      let start = scheduler.now();
+      let ticks = Arc::new(Mutex::new(vec![]));
+      let c_ticks = ticks.clone();
+      let c_scheduler = scheduler.clone();
+      interval(ms(10), scheduler.clone())
+        .with_policy(policy)
+        .take(4)
+        .subscribe(move |_| {
+          c_ticks.lock().unwrap().push(c_scheduler.now() - start);
+          // The task takes 3ms of the period to run.
+          c_scheduler.advance_by(ms(3));
+        });
+      scheduler.advance_by(ms(100));
+
+      assert_eq!(
+        *ticks.lock().unwrap(),
+        vec![ms(10), ms(20), ms(30), ms(40)],
+        "{:?}",
+        policy
+      );
+    }
+  }
+
+  #[test]
+  fn next_deadline() {
+    let ms = Duration::from_millis;
+    let start = Instant::now();
+    let period = ms(10);
+    let late = start + ms(35);
+    assert_eq!(
+      IntervalPolicy::Burst.next_deadline(start, period, late),
+      start + ms(10)
+    );
+    assert_eq!(
+      IntervalPolicy::Delay.next_deadline(start, period, late),
+      start + ms(45)
+    );
+    assert_eq!(
+      IntervalPolicy::Skip.next_deadline(start, period, late),
+      start + ms(40)
+    );
+    assert_eq!(
+      IntervalPolicy::Delay.next_deadline(start, period, start + ms(1)),
+      start + period
+    );
+    assert_eq!(
+      IntervalPolicy::Skip.next_deadline(start, period, start + ms(1)),
+      start + period
+    );
+    let ns = Duration::from_nanos;
+    let pause = Duration::from_secs(3600);
+    assert_eq!(
+      IntervalPolicy::Skip.next_deadline(start, ns(3), start + pause),
+      start + pause + ns(3)
+    );
+  }
+
+  #[cfg(not(target_arch = "wasm32"))]
+  #[test]
+  fn skip_after_pause() {
+    let mut local = LocalPool::new();
+    let ticks = Arc::new(Mutex::new(0));
+    let c_ticks = ticks.clone();
+    let stamp = Instant::now();
+    interval(Duration::from_millis(5), local.spawner())
+      .with_policy(IntervalPolicy::Skip)
+      .take(3)
+      .subscribe(move |_| {
+        *c_ticks.lock().unwrap() += 1;
+        // Pauses long enough to miss several ticks.
+        std::thread::sleep(Duration::from_millis(20));
+      });
+    local.run();
+
+    assert_eq!(*ticks.lock().unwrap(), 3);
+    // Every tick after a pause waits for the next deadline of the cadence.
+    assert!(stamp.elapsed() >= Duration::from_millis(5 + 20 + 5 + 20 + 5));
+  }
 }
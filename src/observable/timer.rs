@@ -1,5 +1,6 @@
+use crate::scheduler::Instant;
 use crate::{impl_helper::*, impl_local_shared_both, prelude::*};
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 // Returns an observable which will emit a single `item`
 // once after a given `dur` using a given `scheduler`
@@ -11,43 +12,35 @@ pub fn timer<Item, S>(
   TimerObservable {
     item,
     dur,
+    at: None,
     scheduler,
   }
 }
 
 // Returns an observable which will emit a single `item`
 // once at a given timestamp `at` using a given `scheduler`.
-// If timestamp `at` < `Instant::now()`, the observable will emit the item
-// immediately
+// If timestamp `at` < `scheduler.now()` when subscribed, the observable will
+// emit the item immediately
 pub fn timer_at<Item, S>(
   item: Item,
   at: Instant,
   scheduler: S,
 ) -> TimerObservable<Item, S> {
-  let duration = get_duration_from_instant(at);
   TimerObservable {
     item,
-    dur: duration,
+    dur: Duration::default(),
+    at: Some(at),
     scheduler,
   }
 }
 
-// Calculates the duration between `Instant::now()` and a given `instant`.
-// Returns `Duration::default()` when `instant` is a timestamp in the past
-fn get_duration_from_instant(instant: Instant) -> Duration {
-  let now = Instant::now();
-  match instant > now {
-    true => instant - now,
-    false => Duration::default(),
-  }
-}
-
 // Emitter for `observable::timer` and `observable::timer_at` holding the
-// `item` that will be emitted, a `dur` when this will happen and the used
-// `scheduler`
+// `item` that will be emitted, a `dur` or a timestamp `at` when this will
+// happen and the used `scheduler`
 pub struct TimerObservable<Item, S> {
   item: Item,
   dur: Duration,
+  at: Option<Instant>,
   scheduler: S,
 }
 
@@ -60,12 +53,15 @@ impl_local_shared_both! {
   impl<Item, S> TimerObservable<Item, S>;
   type Unsub = SpawnHandle;
   macro method($self:ident, $observer: ident, $ctx: ident) {
+    let dur = $self.at.map_or($self.dur, |at| {
+      at.saturating_duration_since($self.scheduler.now())
+    });
     $self.scheduler.schedule(
       move |_| {
         $observer.next($self.item);
         $observer.complete();
       },
-      Some($self.dur),
+      Some(dur),
       1,
     )
   }
@@ -82,8 +78,8 @@ mod tests {
   use futures::executor::LocalPool;
   #[cfg(not(target_arch = "wasm32"))]
   use futures::executor::ThreadPool;
-  use std::sync::atomic::{AtomicBool, AtomicI32, AtomicUsize, Ordering};
   use std::sync::Arc;
+  use std::sync::atomic::{AtomicBool, AtomicI32, AtomicUsize, Ordering};
   use std::time::{Duration, Instant};
 
   #[test]
@@ -354,4 +350,22 @@ mod tests {
     assert!(now.elapsed() < duration);
     assert!(is_completed.load(Ordering::Relaxed));
   }
+
+  #[test]
+  fn timer_at_virtual_time() {
+    use crate::test_scheduler::TestScheduler;
+    let scheduler = TestScheduler::default();
+    let at = scheduler.now() + Duration::from_secs(60);
+    let timer = observable::timer_at(1, at, scheduler.clone());
+    scheduler.advance_by(Duration::from_secs(20));
+
+    let emitted = Arc::new(AtomicI32::new(0));
+    let c_emitted = emitted.clone();
+    timer.subscribe(move |v| c_emitted.store(v, Ordering::Relaxed));
+
+    scheduler.advance_by(Duration::from_secs(39));
+    assert_eq!(emitted.load(Ordering::Relaxed), 0);
+    scheduler.advance_by(Duration::from_secs(1));
+    assert_eq!(emitted.load(Ordering::Relaxed), 1);
+  }
 }
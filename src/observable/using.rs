@@ -0,0 +1,222 @@
+use crate::{impl_helper::*, impl_local_shared_both, prelude::*};
+
+/// Creates an observable that uses a resource which will be disposed at the
+/// same time as the observable.
+///
+/// On each subscription, `resource_factory` creates a fresh resource and
+/// `observable_factory` builds the observable to subscribe from it. The
+/// resource is dropped when the observable completes, errors or is
+/// unsubscribed, whichever comes first.
+///
+/// # Example
+///
+/// ```
+/// use rxrust::prelude::*;
+///
+/// struct Cursor(Vec<i32>);
+///
+/// impl Drop for Cursor {
+///   fn drop(&mut self) { println!("cursor closed"); }
+/// }
+///
+/// observable::using(
+///   || Cursor(vec![1, 2, 3]),
+///   |cursor: &Cursor| observable::from_iter(cursor.0.clone()),
+/// )
+/// .subscribe(|v| println!("{}", v));
+///
+/// // print log:
+/// // 1
+/// // 2
+/// // 3
+/// // cursor closed
+/// ```
+pub fn using<R, S, RF, OF>(
+  resource_factory: RF,
+  observable_factory: OF,
+) -> UsingObservable<RF, OF>
+where
+  RF: FnOnce() -> R,
+  OF: FnOnce(&R) -> S,
+{
+  UsingObservable {
+    resource_factory,
+    observable_factory,
+  }
+}
+
+#[derive(Clone)]
+pub struct UsingObservable<RF, OF> {
+  resource_factory: RF,
+  observable_factory: OF,
+}
+
+impl<R, S, RF, OF> Observable for UsingObservable<RF, OF>
+where
+  RF: FnOnce() -> R,
+  OF: FnOnce(&R) -> S,
+  S: Observable,
+{
+  type Item = S::Item;
+  type Err = S::Err;
+}
+
+impl_local_shared_both! {
+  impl<R, S, RF, OF> UsingObservable<RF, OF>;
+  type Unsub = @ctx::RcMultiSubscription;
+  macro method($self: ident, $observer: ident, $ctx: ident) {
+    let resource = ($self.resource_factory)();
+    let source = ($self.observable_factory)(&resource);
+    let subscription = $ctx::RcMultiSubscription::default();
+    subscription.add(ResourceSubscription(Some(resource)));
+    subscription.add(source.actual_subscribe(UsingObserver {
+      observer: $observer,
+      subscription: subscription.clone(),
+    }));
+    subscription
+  }
+  where
+    RF: FnOnce() -> R,
+    OF: FnOnce(&R) -> S,
+    R: @ctx::shared_only(Send + Sync +) 'static,
+    S: @ctx::Observable,
+    S::Unsub: 'static
+}
+
+/// Holds the resource of `using` in a subscription, the resource is dropped
+/// when unsubscribed.
+struct ResourceSubscription<R>(Option<R>);
+
+impl<R> SubscriptionLike for ResourceSubscription<R> {
+  #[inline]
+  fn unsubscribe(&mut self) { self.0 = None; }
+
+  #[inline]
+  fn is_closed(&self) -> bool { self.0.is_none() }
+}
+
+struct UsingObserver<O, U> {
+  observer: O,
+  subscription: U,
+}
+
+impl<O, U> Observer for UsingObserver<O, U>
+where
+  O: Observer,
+  U: SubscriptionLike,
+{
+  type Item = O::Item;
+  type Err = O::Err;
+
+  #[inline]
+  fn next(&mut self, value: Self::Item) { self.observer.next(value) }
+
+  fn error(&mut self, err: Self::Err) {
+    self.observer.error(err);
+    self.subscription.unsubscribe();
+  }
+
+  fn complete(&mut self) {
+    self.observer.complete();
+    self.subscription.unsubscribe();
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use crate::prelude::*;
+  use std::cell::Cell;
+  use std::rc::Rc;
+
+  struct Resource(Rc<Cell<i32>>);
+
+  impl Drop for Resource {
+    fn drop(&mut self) { self.0.set(self.0.get() - 1); }
+  }
+
+  fn resource(alive: &Rc<Cell<i32>>) -> Resource {
+    alive.set(alive.get() + 1);
+    Resource(alive.clone())
+  }
+
+  #[test]
+  fn dispose_on_complete() {
+    let alive = Rc::new(Cell::new(0));
+    let c_alive = alive.clone();
+    let mut values = vec![];
+    observable::using(
+      || resource(&c_alive),
+      |r: &Resource| observable::of(r.0.get()),
+    )
+    .subscribe(|v| values.push(v));
+
+    assert_eq!(values, vec![1]);
+    assert_eq!(alive.get(), 0);
+  }
+
+  #[test]
+  fn dispose_on_error() {
+    let alive = Rc::new(Cell::new(0));
+    let c_alive = alive.clone();
+    let mut subject = LocalSubject::new();
+    let c_subject = subject.clone();
+    let error = MutRc::own(None);
+    let c_error = error.clone();
+    observable::using(|| resource(&c_alive), |_: &Resource| c_subject)
+      .subscribe_err(|_: i32| {}, move |e| *c_error.rc_deref_mut() = Some(e));
+
+    assert_eq!(alive.get(), 1);
+    subject.error("oops");
+    assert_eq!(alive.get(), 0);
+    assert_eq!(*error.rc_deref(), Some("oops"));
+  }
+
+  #[test]
+  fn dispose_on_unsubscribe() {
+    let alive = Rc::new(Cell::new(0));
+    let c_alive = alive.clone();
+    let o = observable::using(
+      move || resource(&c_alive),
+      |_: &Resource| observable::never(),
+    );
+
+    let mut s1 = o.clone().subscribe(|_| {});
+    let mut s2 = o.subscribe(|_| {});
+    assert_eq!(alive.get(), 2);
+
+    s1.unsubscribe();
+    assert_eq!(alive.get(), 1);
+    s2.unsubscribe();
+    assert_eq!(alive.get(), 0);
+  }
+
+  #[cfg(not(target_arch = "wasm32"))]
+  #[test]
+  fn shared() {
+    use std::sync::{
+      Arc,
+      atomic::{AtomicBool, Ordering},
+    };
+
+    struct Flag(Arc<AtomicBool>);
+    impl Drop for Flag {
+      fn drop(&mut self) { self.0.store(true, Ordering::Relaxed); }
+    }
+
+    let dropped = Arc::new(AtomicBool::new(false));
+    let c_dropped = dropped.clone();
+    let mut subject = SharedSubject::new();
+    let c_subject = subject.clone();
+    observable::using(
+      move || Flag(c_dropped),
+      |_: &Flag| c_subject.into_shared(),
+    )
+    .into_shared()
+    .subscribe(|_: i32| {});
+
+    subject.next(1);
+    assert!(!dropped.load(Ordering::Relaxed));
+    subject.complete();
+    assert!(dropped.load(Ordering::Relaxed));
+  }
+}
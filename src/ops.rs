@@ -1,3 +1,4 @@
+pub mod auto_connect;
 pub mod box_it;
 pub mod buffer;
 pub mod combine_latest;
@@ -14,6 +15,7 @@ pub mod group_by;
 pub mod last;
 pub mod map;
 pub mod map_to;
+pub mod materialize;
 pub mod merge;
 pub mod merge_all;
 pub mod observe_on;
@@ -21,6 +23,7 @@ pub mod pairwise;
 pub mod ref_count;
 pub mod sample;
 pub mod scan;
+pub mod share;
 pub mod skip;
 pub mod skip_last;
 pub mod skip_until;
@@ -33,6 +36,9 @@ pub mod take_until;
 pub mod take_while;
 pub mod tap;
 pub mod throttle_time;
+pub mod time_interval;
+pub mod timestamp;
+pub mod unsubscribe_on;
 pub mod with_latest_from;
 pub mod zip;
 
@@ -0,0 +1,179 @@
+/// Make a ConnectableObservable connect itself once a number of observers
+/// subscribed to it.
+///
+/// The connection is never closed by unsubscribing the observers, it is
+/// handed to a callback, so its owner can close it.
+use crate::{impl_helper::*, impl_local_shared_both, prelude::*};
+
+#[derive(Clone)]
+pub struct AutoConnectOp<R>(pub(crate) R);
+
+pub struct InnerAutoConnect<Src, Sbj, F, C> {
+  subject: Sbj,
+  pending: Option<(ConnectableObservable<Src, Sbj, F>, C)>,
+  remaining: usize,
+}
+
+impl<Src, Sbj, F, C> InnerAutoConnect<Src, Sbj, F, C> {
+  pub(crate) fn new(
+    connectable: ConnectableObservable<Src, Sbj, F>,
+    n: usize,
+    on_connect: C,
+  ) -> Self
+  where
+    Sbj: SubjectLike<Src>,
+    C: FnOnce(Sbj::Connection),
+  {
+    let mut inner = InnerAutoConnect {
+      subject: connectable.fork(),
+      pending: Some((connectable, on_connect)),
+      remaining: n,
+    };
+    if n == 0 {
+      inner.connect()
+    }
+    inner
+  }
+
+  /// Counts an observer, returns the connectable to connect if it was the
+  /// last one awaited.
+  fn arrive(&mut self) -> Option<(ConnectableObservable<Src, Sbj, F>, C)> {
+    self.remaining = self.remaining.saturating_sub(1);
+    if self.remaining == 0 {
+      self.pending.take()
+    } else {
+      None
+    }
+  }
+
+  fn connect(&mut self)
+  where
+    Sbj: SubjectLike<Src>,
+    C: FnOnce(Sbj::Connection),
+  {
+    if let Some((connectable, on_connect)) = self.pending.take() {
+      on_connect(connectable.connect());
+    }
+  }
+}
+
+impl<Src, Sbj, F, C> Observable
+  for AutoConnectOp<MutRc<InnerAutoConnect<Src, Sbj, F, C>>>
+where
+  Sbj: Observable,
+{
+  type Item = Sbj::Item;
+  type Err = Sbj::Err;
+}
+
+impl<Src, Sbj, F, C> Observable
+  for AutoConnectOp<MutArc<InnerAutoConnect<Src, Sbj, F, C>>>
+where
+  Sbj: Observable,
+{
+  type Item = Sbj::Item;
+  type Err = Sbj::Err;
+}
+
+impl_local_shared_both! {
+  impl<Src, Sbj, F, C> AutoConnectOp<@ctx::Rc<InnerAutoConnect<Src, Sbj, F, C>>>;
+  type Unsub = Sbj::Unsub;
+  macro method($self: ident, $observer: ident, $ctx: ident) {
+    let (subject, pending) = {
+      let mut inner = $self.0.rc_deref_mut();
+      (inner.subject.clone(), inner.arrive())
+    };
+    let subscription = subject.actual_subscribe($observer);
+    if let Some((connectable, on_connect)) = pending {
+      on_connect(connectable.connect());
+    }
+    subscription
+  }
+  where
+    Sbj: SubjectLike<Src> + @ctx::Observable,
+    C: FnOnce(<Sbj as SubjectLike<Src>>::Connection)
+}
+
+#[cfg(test)]
+mod test {
+  use crate::prelude::*;
+  use crate::test_observer::TestObserver;
+
+  #[test]
+  fn connect_on_nth_subscriber() {
+    let mut connection = None;
+    let first = TestObserver::<i32, ()>::new();
+    let second = TestObserver::new();
+    let mut subject = LocalSubject::new();
+    {
+      let auto = subject
+        .clone()
+        .publish()
+        .auto_connect(2, |c| connection = Some(c));
+      let mut s1 = auto.clone().actual_subscribe(first.clone());
+      subject.next(1);
+      first.assert_values(&[]);
+
+      auto.actual_subscribe(second.clone());
+      subject.next(2);
+      s1.unsubscribe();
+    }
+    subject.next(3);
+
+    first.assert_values(&[2]);
+    second.assert_values(&[2, 3]);
+
+    connection.unwrap().unsubscribe();
+    subject.next(4);
+    second.assert_values(&[2, 3]);
+  }
+
+  #[test]
+  fn never_disconnect() {
+    let observer = TestObserver::<i32, ()>::new();
+    let mut subject = LocalSubject::new();
+    let auto = subject.clone().publish().auto_connect(1, |_| {});
+    auto
+      .clone()
+      .actual_subscribe(TestObserver::new())
+      .unsubscribe();
+    subject.next(1);
+    auto.actual_subscribe(observer.clone());
+    subject.next(2);
+
+    observer.assert_values(&[2]);
+    assert_eq!(subject.teardown_size(), 1);
+  }
+
+  #[test]
+  fn connect_immediately() {
+    let mut connected = false;
+    let observer = TestObserver::<i32, ()>::new();
+    let auto = observable::from_iter(0..3)
+      .publish_last::<LocalSubject<'_, _, _>>()
+      .auto_connect(0, |_| connected = true);
+    auto.actual_subscribe(observer.clone());
+
+    assert!(connected);
+    observer.assert_values(&[2]).assert_complete();
+  }
+
+  #[cfg(not(target_arch = "wasm32"))]
+  #[test]
+  fn shared() {
+    let connection = MutArc::own(None);
+    let c_connection = connection.clone();
+    let observer = TestObserver::<i32, ()>::new();
+    let mut subject = SharedSubject::new();
+    let auto = subject
+      .clone()
+      .publish()
+      .auto_connect(1, move |c| *c_connection.rc_deref_mut() = Some(c));
+    auto.into_shared().actual_subscribe(observer.clone());
+    subject.next(1);
+    connection.rc_deref_mut().take().unwrap().unsubscribe();
+    subject.next(2);
+
+    observer.assert_values(&[1]);
+  }
+}
@@ -94,6 +94,7 @@ macro_rules! new_buffer_time_observer {
       },
       $time,
       None,
+      IntervalPolicy::default(),
     );
     let handler = $ctx::Rc::own(handler);
     BufferWithTimeObserver {
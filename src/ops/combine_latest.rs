@@ -156,13 +156,13 @@ mod tests {
   use std::rc::Rc;
   use std::time::Duration;
 
-  use crate::test_scheduler::ManualScheduler;
+  use crate::test_scheduler::TestScheduler;
 
   use super::*;
 
   #[test]
   fn combine_latest_base() {
-    let scheduler = ManualScheduler::now();
+    let scheduler = TestScheduler::default();
     let x = Rc::new(RefCell::new(vec![]));
 
     let interval =
@@ -179,7 +179,7 @@ mod tests {
           x_c.borrow_mut().push(v);
         });
 
-      scheduler.advance_and_run(Duration::from_millis(1), 10);
+      scheduler.advance_by(Duration::from_millis(10));
       {
         let v = x.borrow();
         assert_eq!(v.len(), 7);
@@ -1,6 +1,7 @@
 use crate::prelude::*;
+use crate::scheduler::Instant;
 use crate::{impl_helper::*, impl_local_shared_both};
-use std::time::{Duration, Instant};
+use std::time::Duration;
 #[derive(Clone)]
 pub struct DebounceOp<S, SD> {
   pub(crate) source: S,
@@ -62,7 +63,7 @@ macro_rules! impl_observer {
       fn next(&mut self, value: Self::Item) {
         let c_observer = self.clone();
         let mut inner = self.rc_deref_mut();
-        let updated = Some(Instant::now());
+        let updated = Some(inner.scheduler.now());
         inner.last_updated = updated;
         inner.trailing_value = Some(value);
         let delay = inner.delay;
@@ -145,6 +146,27 @@ mod tests {
     assert_eq!(&*x_c.rc_deref(), &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
   }
 
+  #[test]
+  fn virtual_time() {
+    use crate::test_scheduler::TestScheduler;
+    let scheduler = TestScheduler::default();
+    let x = MutRc::own(vec![]);
+    let x_c = x.clone();
+    let mut subject = LocalSubject::new();
+    subject
+      .clone()
+      .debounce(Duration::from_millis(10), scheduler.clone())
+      .subscribe(move |v| x_c.rc_deref_mut().push(v));
+
+    subject.next(1);
+    scheduler.advance_by(Duration::from_millis(5));
+    subject.next(2);
+    scheduler.advance_by(Duration::from_millis(9));
+    assert!(x.rc_deref().is_empty());
+    scheduler.advance_by(Duration::from_millis(1));
+    assert_eq!(&*x.rc_deref(), &[2]);
+  }
+
   #[cfg(not(target_arch = "wasm32"))]
   #[test]
   fn fork_and_shared() {
@@ -1,3 +1,4 @@
+use crate::scheduler::Instant;
 use crate::{impl_helper::*, impl_local_shared_both, prelude::*};
 use std::time::Duration;
 
@@ -5,6 +6,7 @@ use std::time::Duration;
 pub struct DelayOp<S, SD> {
   pub(crate) source: S,
   pub(crate) delay: Duration,
+  pub(crate) at: Option<Instant>,
   pub(crate) scheduler: SD,
 }
 
@@ -19,11 +21,14 @@ impl_local_shared_both! {
   macro method($self: ident, $observer: ident, $ctx: ident){
     let subscription = $ctx::RcMultiSubscription::default();
     let c_subscription = subscription.clone();
+    let delay = $self.at.map_or($self.delay, |at| {
+      at.saturating_duration_since($self.scheduler.now())
+    });
     let handle = $self.scheduler.schedule(
       move |_| {
         c_subscription.add($self.source.actual_subscribe($observer));
       },
-      Some($self.delay),
+      Some(delay),
       (),
     );
     subscription.add(handle);
@@ -80,4 +85,23 @@ mod tests {
     assert!(stamp.elapsed() > Duration::from_millis(50));
     assert_eq!(*value.borrow(), 1);
   }
+
+  #[test]
+  fn delay_at_virtual_time() {
+    use crate::test_scheduler::TestScheduler;
+    let scheduler = TestScheduler::default();
+    let value = MutRc::own(0);
+    let c_value = value.clone();
+    observable::of(1)
+      .delay_at(
+        scheduler.now() + Duration::from_millis(50),
+        scheduler.clone(),
+      )
+      .subscribe(move |v| *c_value.rc_deref_mut() = v);
+
+    scheduler.advance_by(Duration::from_millis(49));
+    assert_eq!(*value.rc_deref(), 0);
+    scheduler.advance_by(Duration::from_millis(1));
+    assert_eq!(*value.rc_deref(), 1);
+  }
 }
@@ -1,195 +1,507 @@
 use crate::prelude::*;
 
-use std::{clone::Clone, cmp::Eq, collections::HashSet, hash::Hash};
+use std::{cmp::Eq, collections::HashMap, hash::Hash};
 
-/// Observer filtering out the data that does not match its key.
+/// Observable of the items of one group, emitted by the `group_by` family of
+/// operators. The items are routed to it by a subject while the source is
+/// subscribed, so the items emitted before a group is subscribed are missed.
 #[derive(Clone)]
-struct GroupObserver<Obs, Discr, Key> {
+pub struct GroupObservable<Key, Subject> {
+  pub key: Key,
+  subject: Subject,
+}
+
+impl<Key, Subject> Observable for GroupObservable<Key, Subject>
+where
+  Subject: Observable,
+{
+  type Item = Subject::Item;
+  type Err = Subject::Err;
+}
+
+impl<'a, Key, Subject> LocalObservable<'a> for GroupObservable<Key, Subject>
+where
+  Subject: LocalObservable<'a>,
+{
+  type Unsub = Subject::Unsub;
+  fn actual_subscribe<O>(self, observer: O) -> Self::Unsub
+  where
+    O: Observer<Item = Self::Item, Err = Self::Err> + 'a,
+  {
+    self.subject.actual_subscribe(observer)
+  }
+}
+
+#[cfg(not(all(target_arch = "wasm32")))]
+impl<Key, Subject> SharedObservable for GroupObservable<Key, Subject>
+where
+  Subject: SharedObservable,
+{
+  type Unsub = Subject::Unsub;
+  fn actual_subscribe<O>(self, observer: O) -> Self::Unsub
+  where
+    O: Observer<Item = Self::Item, Err = Self::Err> + Send + Sync + 'static,
+  {
+    self.subject.actual_subscribe(observer)
+  }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+
+/// GroupByObserver routes the items of its source to the subject of their
+/// key, and emits a GroupObservable for each key returned by the
+/// discriminator function that was not yet encountered.
+pub struct GroupByObserver<Obs, Discr, Sel, Key, Subject, Item> {
   observer: Obs,
   discr: Discr,
-  key: Key,
+  selector: Sel,
+  groups: HashMap<Key, Subject>,
+  _marker: TypeHint<*const Item>,
 }
 
-impl<Obs, Discr, Key, Item, Err> Observer for GroupObserver<Obs, Discr, Key>
+impl<Obs, Discr, Sel, Key, Subject, Item, Err> Observer
+  for GroupByObserver<Obs, Discr, Sel, Key, Subject, Item>
 where
-  Obs: Observer<Item = Item, Err = Err>,
+  Obs: Observer<Item = GroupObservable<Key, Subject>, Err = Err>,
   Discr: FnMut(&Item) -> Key,
-  Key: Hash + Eq,
+  Sel: FnMut(Item) -> Subject::Item,
+  Key: Hash + Clone + Eq,
+  Subject: Observer<Err = Err> + Default + Clone,
+  Err: Clone,
 {
   type Item = Item;
   type Err = Err;
   fn next(&mut self, value: Item) {
-    if (self.discr)(&value) == self.key {
-      self.observer.next(value);
-    }
+    let key = (self.discr)(&value);
+    let mut subject = match self.groups.get(&key) {
+      Some(subject) => subject.clone(),
+      None => {
+        let subject = Subject::default();
+        self.groups.insert(key.clone(), subject.clone());
+        self.observer.next(GroupObservable {
+          key,
+          subject: subject.clone(),
+        });
+        subject
+      }
+    };
+    subject.next((self.selector)(value));
   }
 
-  fn error(&mut self, err: Self::Err) { self.observer.error(err) }
+  fn error(&mut self, err: Self::Err) {
+    self
+      .groups
+      .drain()
+      .for_each(|(_, mut subject)| subject.error(err.clone()));
+    self.observer.error(err)
+  }
 
-  fn complete(&mut self) { self.observer.complete() }
+  fn complete(&mut self) {
+    self
+      .groups
+      .drain()
+      .for_each(|(_, mut subject)| subject.complete());
+    self.observer.complete()
+  }
 }
 
 ///////////////////////////////////////////////////////////////////////////////
 
-/// Observable emitted by the GroupByOp.
+/// Main observable returned by the group_by method.
 #[derive(Clone)]
-pub struct GroupObservable<Source, Discr, Key> {
-  source: Source,
-  discr: Discr,
-  key: Key,
+pub struct GroupByOp<Source, Discr, Sel, Subject> {
+  pub(crate) source: Source,
+  pub(crate) discr: Discr,
+  pub(crate) selector: Sel,
+  pub(crate) _hint: TypeHint<Subject>,
 }
 
-impl<Source, Discr, Key> Observable for GroupObservable<Source, Discr, Key>
+impl<Source, Discr, Sel, Key, Subject> Observable
+  for GroupByOp<Source, Discr, Sel, Subject>
 where
   Source: Observable,
+  Discr: FnMut(&Source::Item) -> Key,
+  Key: Hash + Eq,
 {
-  type Item = Source::Item;
+  type Item = GroupObservable<Key, Subject>;
   type Err = Source::Err;
 }
 
-impl<'a, Source, Discr, Key> LocalObservable<'a>
-  for GroupObservable<Source, Discr, Key>
+impl<'a, Source, Discr, Sel, Key, Item> LocalObservable<'a>
+  for GroupByOp<Source, Discr, Sel, LocalSubject<'a, Item, Source::Err>>
 where
   Source: LocalObservable<'a>,
   Source::Item: 'a,
-  Discr: FnMut(&Source::Item) -> Key + Clone + 'a,
+  Source::Err: Clone + 'a,
+  Discr: FnMut(&Source::Item) -> Key + 'a,
+  Sel: FnMut(Source::Item) -> Item + 'a,
   Key: Hash + Clone + Eq + 'a,
+  Item: Clone + 'a,
 {
   type Unsub = Source::Unsub;
   fn actual_subscribe<O>(self, observer: O) -> Self::Unsub
   where
     O: Observer<Item = Self::Item, Err = Self::Err> + 'a,
   {
-    self.source.actual_subscribe(GroupObserver {
+    self.source.actual_subscribe(GroupByObserver {
       observer,
       discr: self.discr,
-      key: self.key,
+      selector: self.selector,
+      groups: HashMap::new(),
+      _marker: TypeHint::new(),
     })
   }
 }
 
-impl<Source, Discr, Key> SharedObservable
-  for GroupObservable<Source, Discr, Key>
+#[cfg(not(all(target_arch = "wasm32")))]
+impl<Source, Discr, Sel, Key, Item> SharedObservable
+  for GroupByOp<Source, Discr, Sel, SharedSubject<Item, Source::Err>>
 where
   Source: SharedObservable,
   Source::Item: Send + Sync + 'static,
-  Discr: FnMut(&Source::Item) -> Key + Clone + Send + Sync + 'static,
+  Source::Err: Clone + Send + Sync + 'static,
+  Discr: FnMut(&Source::Item) -> Key + Send + Sync + 'static,
+  Sel: FnMut(Source::Item) -> Item + Send + Sync + 'static,
   Key: Hash + Clone + Eq + Send + Sync + 'static,
+  Item: Clone + Send + Sync + 'static,
 {
   type Unsub = Source::Unsub;
   fn actual_subscribe<O>(self, observer: O) -> Self::Unsub
   where
     O: Observer<Item = Self::Item, Err = Self::Err> + Send + Sync + 'static,
   {
-    self.source.actual_subscribe(GroupObserver {
+    self.source.actual_subscribe(GroupByObserver {
       observer,
       discr: self.discr,
-      key: self.key,
+      selector: self.selector,
+      groups: HashMap::new(),
+      _marker: TypeHint::new(),
     })
   }
 }
 
 ///////////////////////////////////////////////////////////////////////////////
 
-/// GroupByObserver keeps track of its source observable. Produces
-/// GroupObservable objects for each key returned by the discriminator
-/// function that was not yet encountered.
-#[derive(Clone)]
-pub struct GroupByObserver<Obs, Source, Discr, Key, Item> {
-  observer: Obs,
-  source: Source,
-  discr: Discr,
-  keys: HashSet<Key>,
-  _marker: TypeHint<*const Item>,
+/// The groups of `group_by_until` still open, each with the subscription of
+/// its duration observable. Unsubscribing it drops all the groups.
+pub struct GroupMap<Key, Subject, U> {
+  closed: bool,
+  next_id: usize,
+  groups: HashMap<Key, GroupEntry<Subject, U>>,
+}
+
+struct GroupEntry<Subject, U> {
+  id: usize,
+  subject: Subject,
+  duration: Option<U>,
 }
 
-impl<'a, Obs, Source, Discr, Key, Item, Err> Observer
-  for GroupByObserver<Obs, Source, Discr, Key, Item>
+impl<Key, Subject, U> Default for GroupMap<Key, Subject, U> {
+  fn default() -> Self {
+    GroupMap {
+      closed: false,
+      next_id: 0,
+      groups: HashMap::new(),
+    }
+  }
+}
+
+impl<Key, Subject, U> GroupMap<Key, Subject, U>
 where
-  Obs: Observer<Item = GroupObservable<Source, Discr, Key>, Err = Err>,
-  Source: Observable + Clone,
-  Discr: FnMut(&Item) -> Key + Clone,
-  Key: Hash + Clone + Eq,
+  Key: Hash + Eq,
+  U: SubscriptionLike,
 {
-  type Item = Item;
-  type Err = Err;
-  fn next(&mut self, value: Item) {
-    let key = (self.discr)(&value);
-    if !self.keys.contains(&key) {
-      let source = self.source.clone();
-      let discr = self.discr.clone();
-      self.keys.insert(key.clone());
-      self.observer.next(GroupObservable { source, discr, key });
-    };
+  /// Removes all the groups and returns their subjects.
+  fn drain(&mut self) -> Vec<Subject> {
+    self
+      .groups
+      .drain()
+      .map(|(_, entry)| {
+        if let Some(mut duration) = entry.duration {
+          duration.unsubscribe();
+        }
+        entry.subject
+      })
+      .collect()
+  }
+
+  /// Removes the group `id` of `key` if it's still open.
+  fn remove(&mut self, key: &Key, id: usize) -> Option<Subject> {
+    if self.groups.get(key)?.id != id {
+      return None;
+    }
+    let entry = self.groups.remove(key)?;
+    if let Some(mut duration) = entry.duration {
+      duration.unsubscribe();
+    }
+    Some(entry.subject)
+  }
+}
+
+impl<Key, Subject, U> SubscriptionLike for GroupMap<Key, Subject, U>
+where
+  Key: Hash + Eq,
+  U: SubscriptionLike,
+{
+  fn unsubscribe(&mut self) {
+    self.closed = true;
+    self.drain();
   }
 
-  fn error(&mut self, err: Self::Err) { self.observer.error(err) }
+  #[inline]
+  fn is_closed(&self) -> bool { self.closed }
+}
 
-  fn complete(&mut self) { self.observer.complete() }
+/// Observer of the duration observable of a group, completes the group on
+/// the first notification it receives.
+pub struct DurationObserver<Key, Groups, Item, Err> {
+  key: Key,
+  id: usize,
+  groups: Groups,
+  _marker: TypeHint<(Item, Err)>,
 }
 
-///////////////////////////////////////////////////////////////////////////////
+/// GroupByUntilObserver works as `GroupByObserver`, and subscribes the
+/// duration observable of each group it creates.
+pub struct GroupByUntilObserver<Obs, Discr, Sel, Dur, Groups, Item> {
+  observer: Obs,
+  discr: Discr,
+  selector: Sel,
+  duration: Dur,
+  groups: Groups,
+  _marker: TypeHint<*const Item>,
+}
 
-/// Main observable returned by the group_by method.
+macro_rules! impl_group_by_until_observers {
+  ($rc: ident, [$($dur: tt)*], $($bounds: tt)*) => {
+    impl<Key, Subject, U, Item, Err> Observer
+      for DurationObserver<Key, $rc<GroupMap<Key, Subject, U>>, Item, Err>
+    where
+      Key: Hash + Eq,
+      Subject: Observer,
+      U: SubscriptionLike,
+    {
+      type Item = Item;
+      type Err = Err;
+
+      #[inline]
+      fn next(&mut self, _: Item) { self.complete() }
+
+      #[inline]
+      fn error(&mut self, _: Err) { self.complete() }
+
+      fn complete(&mut self) {
+        let subject = self.groups.rc_deref_mut().remove(&self.key, self.id);
+        if let Some(mut subject) = subject {
+          subject.complete();
+        }
+      }
+    }
+
+    impl<Obs, Discr, Sel, Dur, DurObs, U, Key, Subject, Item, Err> Observer
+      for GroupByUntilObserver<
+        Obs,
+        Discr,
+        Sel,
+        Dur,
+        $rc<GroupMap<Key, Subject, U>>,
+        Item,
+      >
+    where
+      Obs: Observer<Item = GroupObservable<Key, Subject>, Err = Err>,
+      Discr: FnMut(&Item) -> Key,
+      Sel: FnMut(Item) -> Subject::Item,
+      Dur: FnMut(&GroupObservable<Key, Subject>) -> DurObs,
+      Key: Hash + Clone + Eq $($bounds)*,
+      Subject: Observer<Err = Err> + Default + Clone $($bounds)*,
+      Err: Clone,
+      U: SubscriptionLike $($bounds)*,
+      DurObs: $($dur)*,
+      DurObs::Item: 'static,
+      DurObs::Err: 'static,
+    {
+      type Item = Item;
+      type Err = Err;
+
+      fn next(&mut self, value: Item) {
+        let key = (self.discr)(&value);
+        let subject = self
+          .groups
+          .rc_deref()
+          .groups
+          .get(&key)
+          .map(|entry| entry.subject.clone());
+        let mut subject = match subject {
+          Some(subject) => subject,
+          None => {
+            let subject = Subject::default();
+            let id = {
+              let mut groups = self.groups.rc_deref_mut();
+              if groups.closed {
+                return;
+              }
+              let id = groups.next_id;
+              groups.next_id += 1;
+              groups.groups.insert(
+                key.clone(),
+                GroupEntry {
+                  id,
+                  subject: subject.clone(),
+                  duration: None,
+                },
+              );
+              id
+            };
+            let group = GroupObservable {
+              key: key.clone(),
+              subject: subject.clone(),
+            };
+            let duration = (self.duration)(&group);
+            self.observer.next(group);
+            let mut unsub = duration.actual_subscribe(DurationObserver {
+              key: key.clone(),
+              id,
+              groups: self.groups.clone(),
+              _marker: TypeHint::new(),
+            });
+            let mut groups = self.groups.rc_deref_mut();
+            match groups.groups.get_mut(&key) {
+              Some(entry) if entry.id == id => entry.duration = Some(unsub),
+              // The duration already ended the group.
+              _ => {
+                drop(groups);
+                unsub.unsubscribe();
+              }
+            }
+            subject
+          }
+        };
+        subject.next((self.selector)(value));
+      }
+
+      fn error(&mut self, err: Self::Err) {
+        let subjects = self.groups.rc_deref_mut().drain();
+        subjects
+          .into_iter()
+          .for_each(|mut subject| subject.error(err.clone()));
+        self.observer.error(err)
+      }
+
+      fn complete(&mut self) {
+        let subjects = self.groups.rc_deref_mut().drain();
+        subjects.into_iter().for_each(|mut subject| subject.complete());
+        self.observer.complete()
+      }
+    }
+  };
+}
+
+impl_group_by_until_observers!(MutRc, [LocalObservable<'static, Unsub = U>], + 'static);
+#[cfg(not(all(target_arch = "wasm32")))]
+impl_group_by_until_observers!(MutArc, [SharedObservable<Unsub = U>], + Send + Sync + 'static);
+
+/// Observable returned by the group_by_until method.
 #[derive(Clone)]
-pub struct GroupByOp<Source, Discr> {
+pub struct GroupByUntilOp<Source, Discr, Sel, Dur, Subject> {
   pub(crate) source: Source,
   pub(crate) discr: Discr,
+  pub(crate) selector: Sel,
+  pub(crate) duration: Dur,
+  pub(crate) _hint: TypeHint<Subject>,
 }
 
-impl<Source, Discr, Key> Observable for GroupByOp<Source, Discr>
+impl<Source, Discr, Sel, Dur, Key, Subject> Observable
+  for GroupByUntilOp<Source, Discr, Sel, Dur, Subject>
 where
   Source: Observable,
   Discr: FnMut(&Source::Item) -> Key,
   Key: Hash + Eq,
 {
-  type Item = GroupObservable<Source, Discr, Key>;
+  type Item = GroupObservable<Key, Subject>;
   type Err = Source::Err;
 }
 
-impl<'a, Source, Discr, Key> LocalObservable<'a> for GroupByOp<Source, Discr>
+macro_rules! group_by_until_subscribe {
+  ($self: ident, $observer: ident, $rc: ident, $subscription: ident) => {{
+    let groups = $rc::own(GroupMap::default());
+    let subscription = $subscription::default();
+    subscription.add(groups.clone());
+    subscription.add($self.source.actual_subscribe(GroupByUntilObserver {
+      observer: $observer,
+      discr: $self.discr,
+      selector: $self.selector,
+      duration: $self.duration,
+      groups,
+      _marker: TypeHint::new(),
+    }));
+    subscription
+  }};
+}
+
+// The groups are held by the subscription, so the local implementation is
+// only for `'static` observers.
+impl<Source, Discr, Sel, Dur, DurObs, Key, Item> LocalObservable<'static>
+  for GroupByUntilOp<
+    Source,
+    Discr,
+    Sel,
+    Dur,
+    LocalSubject<'static, Item, Source::Err>,
+  >
 where
-  Source: LocalObservable<'a> + 'a + Clone,
-  Source::Item: 'a,
-  Discr: FnMut(&Source::Item) -> Key + 'a + Clone,
-  Key: 'a + Hash + Clone + Eq,
+  Source: LocalObservable<'static>,
+  Source::Unsub: 'static,
+  Source::Item: 'static,
+  Source::Err: Clone + 'static,
+  Discr: FnMut(&Source::Item) -> Key + 'static,
+  Sel: FnMut(Source::Item) -> Item + 'static,
+  Dur: FnMut(
+      &GroupObservable<Key, LocalSubject<'static, Item, Source::Err>>,
+    ) -> DurObs
+    + 'static,
+  DurObs: LocalObservable<'static>,
+  DurObs::Item: 'static,
+  DurObs::Err: 'static,
+  DurObs::Unsub: 'static,
+  Key: Hash + Clone + Eq + 'static,
+  Item: Clone + 'static,
 {
-  type Unsub = Source::Unsub;
+  type Unsub = LocalSubscription;
   fn actual_subscribe<O>(self, observer: O) -> Self::Unsub
   where
-    O: Observer<Item = Self::Item, Err = Self::Err> + 'a,
+    O: Observer<Item = Self::Item, Err = Self::Err> + 'static,
   {
-    let source = self.source.clone();
-    self.source.actual_subscribe(GroupByObserver {
-      observer,
-      source,
-      discr: self.discr,
-      keys: HashSet::new(),
-      _marker: TypeHint::new(),
-    })
+    group_by_until_subscribe!(self, observer, MutRc, LocalSubscription)
   }
 }
 
-impl<'a, Source, Discr, Key> SharedObservable for GroupByOp<Source, Discr>
+#[cfg(not(all(target_arch = "wasm32")))]
+impl<Source, Discr, Sel, Dur, DurObs, Key, Item> SharedObservable
+  for GroupByUntilOp<Source, Discr, Sel, Dur, SharedSubject<Item, Source::Err>>
 where
-  Source: SharedObservable + Clone + Send + Sync + 'static,
+  Source: SharedObservable,
+  Source::Unsub: Send + Sync + 'static,
   Source::Item: Send + Sync + 'static,
-  Discr: FnMut(&Source::Item) -> Key + Clone + Send + Sync + 'static,
-  Key: 'a + Hash + Clone + Eq + Send + Sync + 'static,
+  Source::Err: Clone + Send + Sync + 'static,
+  Discr: FnMut(&Source::Item) -> Key + Send + Sync + 'static,
+  Sel: FnMut(Source::Item) -> Item + Send + Sync + 'static,
+  Dur: FnMut(&GroupObservable<Key, SharedSubject<Item, Source::Err>>) -> DurObs
+    + Send
+    + Sync
+    + 'static,
+  DurObs: SharedObservable,
+  DurObs::Item: 'static,
+  DurObs::Err: 'static,
+  DurObs::Unsub: Send + Sync + 'static,
+  Key: Hash + Clone + Eq + Send + Sync + 'static,
+  Item: Clone + Send + Sync + 'static,
 {
-  type Unsub = Source::Unsub;
+  type Unsub = SharedSubscription;
   fn actual_subscribe<O>(self, observer: O) -> Self::Unsub
   where
     O: Observer<Item = Self::Item, Err = Self::Err> + Send + Sync + 'static,
   {
-    let source = self.source.clone();
-    self.source.actual_subscribe(GroupByObserver {
-      observer,
-      source,
-      discr: self.discr,
-      keys: HashSet::new(),
-      _marker: TypeHint::new(),
-    })
+    group_by_until_subscribe!(self, observer, MutArc, SharedSubscription)
   }
 }
 
@@ -220,6 +532,154 @@ mod test {
     s.into_shared().subscribe(|_| {});
   }
 
+  #[test]
+  fn subscribe_source_once() {
+    let subscribed = MutRc::own(0);
+    let c_subscribed = subscribed.clone();
+    let sums = MutRc::own(vec![]);
+    let c_sums = sums.clone();
+    observable::defer(move || {
+      *c_subscribed.rc_deref_mut() += 1;
+      observable::from_iter(0..9)
+    })
+    .group_by(|v| v % 3)
+    .subscribe(move |group| {
+      let sums = c_sums.clone();
+      let key = group.key;
+      group
+        .sum()
+        .subscribe(move |sum| sums.rc_deref_mut().push((key, sum)));
+    });
+
+    assert_eq!(*subscribed.rc_deref(), 1);
+    // The groups complete in no particular order.
+    sums.rc_deref_mut().sort();
+    assert_eq!(&*sums.rc_deref(), &[(0, 9), (1, 12), (2, 15)]);
+  }
+
+  #[test]
+  fn hot_source_and_element() {
+    let mut source = LocalSubject::new();
+    let groups = MutRc::own(vec![]);
+    let c_groups = groups.clone();
+    let values = MutRc::own(vec![]);
+    let c_values = values.clone();
+    let error = MutRc::own(None);
+    let c_error = error.clone();
+    source
+      .clone()
+      .group_by_element(|v: &(char, i32)| v.0, |v: (char, i32)| v.1)
+      .subscribe_err(
+        move |group| {
+          c_groups.rc_deref_mut().push(group.key);
+          let values = c_values.clone();
+          let error = c_error.clone();
+          group.subscribe_err(
+            move |v| values.rc_deref_mut().push(v),
+            move |e| *error.rc_deref_mut() = Some(e),
+          );
+        },
+        |_| {},
+      );
+
+    source.next(('a', 1));
+    source.next(('b', 2));
+    source.next(('a', 3));
+    source.error("oops");
+
+    assert_eq!(&*groups.rc_deref(), &['a', 'b']);
+    assert_eq!(&*values.rc_deref(), &[1, 2, 3]);
+    assert_eq!(*error.rc_deref(), Some("oops"));
+  }
+
+  #[test]
+  fn group_by_until_idle() {
+    use crate::test_scheduler::TestScheduler;
+    use std::time::Duration;
+
+    let scheduler = TestScheduler::default();
+    let c_scheduler = scheduler.clone();
+    let mut source = LocalSubject::new();
+    let groups = MutRc::own(vec![]);
+    let c_groups = groups.clone();
+    source
+      .clone()
+      .group_by_until(
+        |v: &i32| v % 2,
+        |v| v,
+        move |_| {
+          observable::timer((), Duration::from_millis(10), c_scheduler.clone())
+        },
+      )
+      .subscribe(move |group| {
+        let groups = c_groups.clone();
+        let key = group.key;
+        group
+          .reduce_initial(vec![], |mut acc, v| {
+            acc.push(v);
+            acc
+          })
+          .subscribe(move |items| groups.rc_deref_mut().push((key, items)));
+      });
+
+    source.next(0);
+    source.next(1);
+    scheduler.advance_by(Duration::from_millis(5));
+    source.next(2);
+    scheduler.advance_by(Duration::from_millis(5));
+    assert_eq!(&*groups.rc_deref(), &[(0, vec![0, 2]), (1, vec![1])]);
+
+    source.next(4);
+    source.complete();
+    assert_eq!(groups.rc_deref().len(), 3);
+    assert_eq!(groups.rc_deref().last(), Some(&(0, vec![4])));
+    scheduler.advance_by(Duration::from_millis(10));
+    assert_eq!(groups.rc_deref().len(), 3);
+  }
+
+  #[test]
+  fn group_by_until_unsubscribe() {
+    let mut source = LocalSubject::new();
+    let finalized = MutRc::own(0);
+    let c_finalized = finalized.clone();
+    let mut subscription = source
+      .clone()
+      .group_by_until(
+        |v: &i32| *v,
+        |v| v,
+        move |_| {
+          let finalized = c_finalized.clone();
+          observable::never().finalize(move || *finalized.rc_deref_mut() += 1)
+        },
+      )
+      .subscribe(|_| {});
+
+    source.next(1);
+    source.next(2);
+    assert_eq!(*finalized.rc_deref(), 0);
+    subscription.unsubscribe();
+    assert_eq!(*finalized.rc_deref(), 2);
+  }
+
+  #[cfg(not(target_arch = "wasm32"))]
+  #[test]
+  fn group_by_until_shared() {
+    use std::sync::{Arc, Mutex};
+    let mut source = SharedSubject::new();
+    let count = Arc::new(Mutex::new(0));
+    let c_count = count.clone();
+    source
+      .clone()
+      .group_by_until(|v: &i32| *v, |v| v, |group| group.clone().into_shared())
+      .into_shared()
+      .subscribe(move |_| *c_count.lock().unwrap() += 1);
+
+    source.next(1);
+    source.next(1);
+    source.next(2);
+    assert_eq!(*count.lock().unwrap(), 3);
+  }
+
   #[test]
   fn bench() { do_bench(); }
 
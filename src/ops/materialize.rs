@@ -0,0 +1,141 @@
+use crate::{impl_local_shared_both, prelude::*};
+
+#[derive(Clone)]
+pub struct MaterializeOp<S> {
+  pub(crate) source: S,
+}
+
+impl<S: Observable> Observable for MaterializeOp<S> {
+  type Item = Notification<S::Item, S::Err>;
+  type Err = ();
+}
+
+impl_local_shared_both! {
+  impl<S> MaterializeOp<S>;
+  type Unsub = S::Unsub;
+  macro method($self: ident, $observer: ident, $ctx: ident) {
+    $self
+      .source
+      .actual_subscribe(MaterializeObserver::new($observer))
+  }
+  where
+    S: @ctx::Observable,
+    S::Item: @ctx::local_only('o) @ctx::shared_only('static),
+    S::Err: @ctx::local_only('o) @ctx::shared_only('static)
+}
+
+#[derive(Clone)]
+pub struct DematerializeOp<S> {
+  pub(crate) source: S,
+}
+
+impl<S, Item, Err> Observable for DematerializeOp<S>
+where
+  S: Observable<Item = Notification<Item, Err>, Err = ()>,
+{
+  type Item = Item;
+  type Err = Err;
+}
+
+impl_local_shared_both! {
+  impl<S, Item, Err> DematerializeOp<S>;
+  type Unsub = S::Unsub;
+  macro method($self: ident, $observer: ident, $ctx: ident) {
+    $self
+      .source
+      .actual_subscribe(NotificationObserver::new($observer))
+  }
+  where
+    S: @ctx::Observable<Item = Notification<Item, Err>, Err = ()>,
+    Item: @ctx::local_only('o) @ctx::shared_only('static),
+    Err: @ctx::local_only('o) @ctx::shared_only('static)
+}
+
+#[cfg(test)]
+mod test {
+  use crate::prelude::*;
+
+  #[test]
+  fn materialize() {
+    let mut notifications = vec![];
+    let mut completed = 0;
+    observable::from_iter(0..3)
+      .materialize()
+      .subscribe_complete(|n| notifications.push(n), || completed += 1);
+
+    assert_eq!(
+      notifications,
+      vec![
+        Notification::Next(0),
+        Notification::Next(1),
+        Notification::Next(2),
+        Notification::Complete
+      ]
+    );
+    assert_eq!(completed, 1);
+  }
+
+  #[test]
+  fn materialize_error() {
+    let mut notifications = vec![];
+    let mut completed = 0;
+    observable::throw("oops")
+      .materialize()
+      .subscribe_complete(|n| notifications.push(n), || completed += 1);
+
+    assert_eq!(notifications, vec![Notification::Error("oops")]);
+    assert_eq!(completed, 1);
+  }
+
+  #[test]
+  fn dematerialize() {
+    let mut values = vec![];
+    let mut error = None;
+    let mut completed = false;
+    observable::from_iter(vec![
+      Notification::Next(1),
+      Notification::Next(2),
+      Notification::Error("oops"),
+      Notification::Next(3),
+    ])
+    .dematerialize()
+    .subscribe_all(
+      |v| values.push(v),
+      |e| error = Some(e),
+      || completed = true,
+    );
+
+    assert_eq!(values, vec![1, 2]);
+    assert_eq!(error, Some("oops"));
+    assert!(!completed);
+  }
+
+  #[test]
+  fn round_trip() {
+    let mut values = vec![];
+    let mut error = None;
+    {
+      let mut subject = LocalSubject::new();
+      subject
+        .clone()
+        .materialize()
+        .dematerialize()
+        .subscribe_err(|v| values.push(v), |e| error = Some(e));
+
+      subject.next(1);
+      subject.next(2);
+      subject.error("oops");
+    }
+
+    assert_eq!(values, vec![1, 2]);
+    assert_eq!(error, Some("oops"));
+  }
+
+  #[cfg(not(target_arch = "wasm32"))]
+  #[test]
+  fn fork_and_shared() {
+    let m = observable::from_iter(0..10).materialize();
+    m.clone().into_shared().subscribe(|_| {});
+    m.dematerialize().into_shared().subscribe(|_| {});
+  }
+}
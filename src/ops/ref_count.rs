@@ -14,26 +14,56 @@
 use crate::{impl_helper::*, impl_local_shared_both, prelude::*};
 
 #[derive(Clone)]
-pub struct RefCount<R>(R);
+pub struct RefCount<R>(pub(crate) R);
 
-pub enum InnerRefCount<Src, Sbj, U> {
-  Connectable(ConnectableObservable<Src, Sbj>),
-  Connected { subject: Sbj, connection: U },
+pub struct InnerRefCount<Src, Sbj, F, U> {
+  connectable: ConnectableObservable<Src, Sbj, F>,
+  connection: Option<U>,
+  connected: bool,
+  count: usize,
 }
 
-impl<Src, Sbj, U> RefCount<MutRc<InnerRefCount<Src, Sbj, U>>> {
-  pub fn local(c: ConnectableObservable<Src, Sbj>) -> Self {
-    RefCount(MutRc::own(InnerRefCount::Connectable(c)))
+impl<Src, Sbj, F, U> InnerRefCount<Src, Sbj, F, U> {
+  pub(crate) fn new(connectable: ConnectableObservable<Src, Sbj, F>) -> Self {
+    InnerRefCount {
+      connectable,
+      connection: None,
+      connected: false,
+      count: 0,
+    }
+  }
+
+  /// Releases an observer, returns the connection to close if it was the
+  /// last one.
+  fn release(&mut self) -> Option<U>
+  where
+    F: FnMut() -> Sbj,
+  {
+    self.count -= 1;
+    if self.count == 0 {
+      self.connected = false;
+      self.connectable.renew();
+      self.connection.take()
+    } else {
+      None
+    }
   }
 }
 
-impl<Src, Sbj, U> RefCount<MutArc<InnerRefCount<Src, Sbj, U>>> {
-  pub fn shared(c: ConnectableObservable<Src, Sbj>) -> Self {
-    RefCount(MutArc::own(InnerRefCount::Connectable(c)))
+impl<Src, Sbj, F, U> RefCount<MutRc<InnerRefCount<Src, Sbj, F, U>>> {
+  pub fn local(c: ConnectableObservable<Src, Sbj, F>) -> Self {
+    RefCount(MutRc::own(InnerRefCount::new(c)))
   }
 }
 
-impl<Src, Sbj, U> Observable for RefCount<MutRc<InnerRefCount<Src, Sbj, U>>>
+impl<Src, Sbj, F, U> RefCount<MutArc<InnerRefCount<Src, Sbj, F, U>>> {
+  pub fn shared(c: ConnectableObservable<Src, Sbj, F>) -> Self {
+    RefCount(MutArc::own(InnerRefCount::new(c)))
+  }
+}
+
+impl<Src, Sbj, F, U> Observable
+  for RefCount<MutRc<InnerRefCount<Src, Sbj, F, U>>>
 where
   Src: Observable,
 {
@@ -41,7 +71,8 @@ where
   type Err = Src::Err;
 }
 
-impl<Src, Sbj, U> Observable for RefCount<MutArc<InnerRefCount<Src, Sbj, U>>>
+impl<Src, Sbj, F, U> Observable
+  for RefCount<MutArc<InnerRefCount<Src, Sbj, F, U>>>
 where
   Src: Observable,
 {
@@ -50,78 +81,95 @@ where
 }
 
 impl_local_shared_both! {
-  impl<Src, Sbj> RefCount<@ctx::Rc<InnerRefCount<Src, Sbj, Src::Unsub>>>;
-  type Unsub = RefCountSubscription<Sbj, Sbj::Unsub, Src::Unsub>;
+  impl<Src, Sbj, F, U> RefCount<@ctx::Rc<InnerRefCount<Src, Sbj, F, U>>>;
+  type Unsub = RefCountSubscription<
+    Sbj::Unsub, @ctx::Rc<InnerRefCount<Src, Sbj, F, U>>
+  >;
 
   macro method($self: ident, $observer: ident, $ctx: ident) {
-    let mut inner = $self.0.rc_deref_mut();
-    match &mut *inner {
-      InnerRefCount::Connectable(c) => {
-        let subject = c.fork();
-        let subscription = c.fork().actual_subscribe($observer);
-        let new_holder : ConnectableObservable<Src, Sbj> = unsafe {
-          std::mem::transmute_copy(c)
-        };
-        let connection = new_holder.connect();
-        let old = std::mem::replace(&mut *inner, InnerRefCount::Connected {
-          subject: subject.clone(),
-          connection: connection.clone()
-        });
-        std::mem::forget(old);
-
-        RefCountSubscription {
-          subject: subject.clone(), subscription, connection
-        }
-       },
-      InnerRefCount::Connected{ subject, connection } => {
-        let subscription = subject.clone().actual_subscribe($observer);
-        RefCountSubscription {
-          subject: subject.clone(),
-          subscription,
-          connection: connection.clone()
-        }
+    let (subject, source) = {
+      let mut inner = $self.0.rc_deref_mut();
+      inner.count += 1;
+      let source = if inner.connected {
+        None
+      } else {
+        inner.connected = true;
+        Some(inner.connectable.source.clone())
+      };
+      (inner.connectable.fork(), source)
+    };
+    let subscription = subject.clone().actual_subscribe($observer);
+    if let Some(source) = source {
+      let mut connection = subject.subscribe_to(source);
+      let mut inner = $self.0.rc_deref_mut();
+      if inner.connected && inner.connection.is_none() {
+        inner.connection = Some(connection);
+      } else {
+        drop(inner);
+        connection.unsubscribe();
       }
     }
+    RefCountSubscription {
+      subscription,
+      ref_count: $self.0.clone(),
+      closed: false,
+    }
   }
   where
-    ConnectableObservable<Src, Sbj>: Connect<Unsub=Src::Unsub>,
-    Src: @ctx::Observable @ctx::local_only(+ 'o) @ctx::shared_only(+ 'static),
-    Src::Unsub: Clone @ctx::local_only(+ 'o) @ctx::shared_only(+'static),
+    Src: Observable + Clone
+      @ctx::local_only(+ 'o) @ctx::shared_only(+ Send + Sync + 'static),
     Src::Item: Clone  @ctx::local_only(+ 'o) @ctx::shared_only(+'static),
     Src::Err: Clone @ctx::local_only(+ 'o) @ctx::shared_only(+'static),
-    Sbj: Observer<Item = Src::Item, Err = Src::Err>
-      + TearDownSize + Clone
+    Sbj: SubjectLike<Src, Connection = U>
       + @ctx::Observable<Item=Src::Item, Err=Src::Err>
-      @ctx::shared_only(+ Send + Sync + 'static) @ctx::local_only(+ 'o)
-
+      @ctx::shared_only(+ Send + Sync + 'static) @ctx::local_only(+ 'o),
+    F: FnMut() -> Sbj
+      @ctx::local_only(+ 'o) @ctx::shared_only(+ Send + Sync + 'static),
+    U: SubscriptionLike
+      @ctx::local_only(+ 'o) @ctx::shared_only(+ Send + Sync + 'static)
 }
 
-pub struct RefCountSubscription<S, U, C> {
-  subject: S,
-  subscription: U,
-  connection: C,
+pub struct RefCountSubscription<S, R> {
+  subscription: S,
+  ref_count: R,
+  closed: bool,
 }
 
-impl<S, U, C> SubscriptionLike for RefCountSubscription<S, U, C>
-where
-  S: TearDownSize,
-  C: SubscriptionLike,
-  U: SubscriptionLike,
-{
-  fn unsubscribe(&mut self) {
-    self.subscription.unsubscribe();
-    if self.subject.teardown_size() == 0 {
-      self.connection.unsubscribe();
-    }
-  }
+macro_rules! impl_ref_count_subscription {
+  ($rc: ident) => {
+    impl<S, Src, Sbj, F, U> SubscriptionLike
+      for RefCountSubscription<S, $rc<InnerRefCount<Src, Sbj, F, U>>>
+    where
+      S: SubscriptionLike,
+      F: FnMut() -> Sbj,
+      U: SubscriptionLike,
+    {
+      fn unsubscribe(&mut self) {
+        if !self.closed {
+          self.closed = true;
+          self.subscription.unsubscribe();
+          let connection = self.ref_count.rc_deref_mut().release();
+          if let Some(mut connection) = connection {
+            connection.unsubscribe();
+          }
+        }
+      }
 
-  #[inline(always)]
-  fn is_closed(&self) -> bool { self.subscription.is_closed() }
+      #[inline(always)]
+      fn is_closed(&self) -> bool {
+        self.closed || self.subscription.is_closed()
+      }
+    }
+  };
 }
 
+impl_ref_count_subscription!(MutRc);
+impl_ref_count_subscription!(MutArc);
+
 #[cfg(test)]
 mod test {
   use crate::prelude::*;
+  use crate::test_observer::TestObserver;
 
   #[test]
   fn smoke() {
@@ -193,6 +241,60 @@ mod test {
       .subscribe(|_| {});
   }
 
+  #[test]
+  fn reconnect_fresh_subject() {
+    let first = TestObserver::<i32, ()>::new();
+    let second = TestObserver::new();
+    let mut subject = LocalSubject::new();
+    let ref_count = subject
+      .clone()
+      .multicast(LocalAsyncSubject::new)
+      .into_ref_count();
+    let mut s1 = ref_count.clone().actual_subscribe(first.clone());
+    subject.next(1);
+    s1.unsubscribe();
+
+    ref_count.actual_subscribe(second.clone());
+    subject.next(2);
+    subject.complete();
+
+    first.assert_values(&[]).assert_not_complete();
+    second.assert_values(&[2]).assert_complete();
+  }
+
+  #[test]
+  fn disconnect_on_zero() {
+    let mut subject = LocalSubject::new();
+    let ref_count = subject.clone().publish().into_ref_count();
+    let mut s1 = ref_count.clone().subscribe(|_: i32| {});
+    let mut s2 = ref_count.clone().subscribe(|_| {});
+
+    assert_eq!(subject.teardown_size(), 1);
+    s1.unsubscribe();
+    s1.unsubscribe();
+    subject.next(1);
+    assert_eq!(subject.teardown_size(), 1);
+    s2.unsubscribe();
+    subject.next(2);
+    assert_eq!(subject.teardown_size(), 0);
+
+    ref_count.subscribe(|_| {});
+    assert_eq!(subject.teardown_size(), 1);
+  }
+
+  #[test]
+  fn behavior_ref_count() {
+    let mut values = vec![];
+    {
+      let mut subject = LocalSubject::new();
+      let ref_count = subject.clone().publish_behavior(0).into_ref_count();
+      ref_count.clone().subscribe(|v| values.push(v));
+      subject.next(1);
+    }
+
+    assert_eq!(values, vec![0, 1]);
+  }
+
   #[test]
   fn bench() { do_bench(); }
 
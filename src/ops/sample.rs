@@ -132,14 +132,14 @@ where
 #[cfg(test)]
 mod test {
   use crate::prelude::*;
-  use crate::test_scheduler::ManualScheduler;
+  use crate::test_scheduler::TestScheduler;
   #[cfg(not(target_arch = "wasm32"))]
   use std::sync::{Arc, Mutex};
   use std::{cell::RefCell, rc::Rc, time::Duration};
 
   #[test]
   fn sample_base() {
-    let scheduler = ManualScheduler::now();
+    let scheduler = TestScheduler::default();
     let x = Rc::new(RefCell::new(vec![]));
 
     let interval =
@@ -156,7 +156,7 @@ mod test {
           x_c.borrow_mut().push(v);
         });
 
-      scheduler.advance_and_run(Duration::from_millis(1), 100);
+      scheduler.advance_by(Duration::from_millis(100));
       assert_eq!(x.borrow().len(), 10);
     };
   }
@@ -0,0 +1,516 @@
+/// Multicast the source through a subject, like `share`, with the
+/// configuration of when the connection to the source and its subject are
+/// reset.
+///
+/// A reset connection is made again when the next observer subscribes,
+/// through a fresh subject made by the connector. Without a reset, later
+/// observers keep subscribing the same subject, even if the source already
+/// completed or errored.
+use crate::{impl_helper::*, impl_local_shared_both, prelude::*};
+use std::time::Duration;
+
+/// When `share_with` resets its connection after its last observer
+/// unsubscribed. Converts from a `bool`, reset at once or never, and from a
+/// `Duration`, the grace period.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResetOnRefCountZero {
+  /// Keep the connection alive.
+  Never,
+  /// Reset as soon as the last observer unsubscribed.
+  Immediately,
+  /// Keep the connection alive for a grace period, and reset it if no
+  /// observer subscribed meanwhile.
+  After(Duration),
+}
+
+impl From<bool> for ResetOnRefCountZero {
+  #[inline]
+  fn from(reset: bool) -> Self {
+    if reset {
+      ResetOnRefCountZero::Immediately
+    } else {
+      ResetOnRefCountZero::Never
+    }
+  }
+}
+
+impl From<Duration> for ResetOnRefCountZero {
+  #[inline]
+  fn from(grace: Duration) -> Self { ResetOnRefCountZero::After(grace) }
+}
+
+/// The configuration of `share_with`.
+///
+/// The `connector` makes the subject of each connection, the `scheduler`
+/// runs the grace period of `reset_on_ref_count_zero`.
+pub struct ShareConfig<F, SD> {
+  pub connector: F,
+  pub reset_on_error: bool,
+  pub reset_on_complete: bool,
+  pub reset_on_ref_count_zero: ResetOnRefCountZero,
+  pub scheduler: SD,
+}
+
+impl<F, SD> ShareConfig<F, SD> {
+  /// A configuration resetting on error, on completion, and as soon as the
+  /// last observer unsubscribed.
+  pub fn new(connector: F, scheduler: SD) -> Self {
+    ShareConfig {
+      connector,
+      reset_on_error: true,
+      reset_on_complete: true,
+      reset_on_ref_count_zero: ResetOnRefCountZero::Immediately,
+      scheduler,
+    }
+  }
+}
+
+#[derive(Clone)]
+pub struct ShareOp<R>(pub(crate) R);
+
+pub struct InnerShare<Src, Sbj, F, SD, U> {
+  source: Src,
+  config: ShareConfig<F, SD>,
+  subject: Option<Sbj>,
+  connection: Option<U>,
+  connected: bool,
+  count: usize,
+  generation: usize,
+  reset_timer: Option<SpawnHandle>,
+}
+
+enum Release<U> {
+  Keep,
+  Disconnect(Option<U>),
+  Grace(Duration, usize),
+}
+
+impl<Src, Sbj, F, SD, U> InnerShare<Src, Sbj, F, SD, U> {
+  pub(crate) fn new(source: Src, config: ShareConfig<F, SD>) -> Self {
+    InnerShare {
+      source,
+      config,
+      subject: None,
+      connection: None,
+      connected: false,
+      count: 0,
+      generation: 0,
+      reset_timer: None,
+    }
+  }
+
+  fn subject(&mut self) -> Sbj
+  where
+    F: FnMut() -> Sbj,
+    Sbj: Clone,
+  {
+    let connector = &mut self.config.connector;
+    self.subject.get_or_insert_with(connector).clone()
+  }
+
+  /// Forgets the connection and the subject, returns the connection to close.
+  fn reset(&mut self) -> Option<U> {
+    if let Some(mut timer) = self.reset_timer.take() {
+      timer.unsubscribe();
+    }
+    self.subject = None;
+    self.connected = false;
+    self.count = 0;
+    self.generation += 1;
+    self.connection.take()
+  }
+
+  fn release(&mut self, generation: usize) -> Release<U> {
+    if self.generation != generation {
+      return Release::Keep;
+    }
+    self.count -= 1;
+    if self.count > 0 {
+      return Release::Keep;
+    }
+    match self.config.reset_on_ref_count_zero {
+      ResetOnRefCountZero::Never => Release::Keep,
+      ResetOnRefCountZero::Immediately => Release::Disconnect(self.reset()),
+      ResetOnRefCountZero::After(grace) => Release::Grace(grace, generation),
+    }
+  }
+
+  /// The grace period is over, reset if no observer subscribed meanwhile.
+  fn expire(&mut self, generation: usize) -> Option<U> {
+    if self.generation == generation && self.count == 0 {
+      self.reset()
+    } else {
+      None
+    }
+  }
+
+  /// The source terminated, reset if `reset` and the connection is current.
+  fn terminate(&mut self, generation: usize, reset: bool) -> Option<U> {
+    if reset && self.generation == generation {
+      self.reset()
+    } else {
+      None
+    }
+  }
+}
+
+impl<Src, Sbj, F, SD, U> Observable
+  for ShareOp<MutRc<InnerShare<Src, Sbj, F, SD, U>>>
+where
+  Src: Observable,
+{
+  type Item = Src::Item;
+  type Err = Src::Err;
+}
+
+impl<Src, Sbj, F, SD, U> Observable
+  for ShareOp<MutArc<InnerShare<Src, Sbj, F, SD, U>>>
+where
+  Src: Observable,
+{
+  type Item = Src::Item;
+  type Err = Src::Err;
+}
+
+impl_local_shared_both! {
+  impl<Src, Sbj, F, SD, U> ShareOp<@ctx::Rc<InnerShare<Src, Sbj, F, SD, U>>>;
+  type Unsub = ShareSubscription<
+    Sbj::Unsub, @ctx::Rc<InnerShare<Src, Sbj, F, SD, U>>
+  >;
+
+  macro method($self: ident, $observer: ident, $ctx: ident) {
+    let (subject, source, generation) = {
+      let mut inner = $self.0.rc_deref_mut();
+      if let Some(mut timer) = inner.reset_timer.take() {
+        timer.unsubscribe();
+      }
+      inner.count += 1;
+      let source = if inner.connected {
+        None
+      } else {
+        inner.connected = true;
+        Some(inner.source.clone())
+      };
+      (inner.subject(), source, inner.generation)
+    };
+    let subscription = subject.clone().actual_subscribe($observer);
+    if let Some(source) = source {
+      let observer = ShareObserver {
+        subject,
+        share: $self.0.clone(),
+        generation,
+      };
+      let mut connection = source.actual_subscribe(observer);
+      let mut inner = $self.0.rc_deref_mut();
+      if inner.connected && inner.generation == generation {
+        inner.connection = Some(connection);
+      } else {
+        drop(inner);
+        connection.unsubscribe();
+      }
+    }
+    ShareSubscription {
+      subscription,
+      share: $self.0.clone(),
+      generation,
+      closed: false,
+    }
+  }
+  where
+    Src: @ctx::Observable<Unsub = U> + Clone
+      @ctx::local_only(+ 'static) @ctx::shared_only(+ Send + Sync + 'static),
+    Src::Item: Clone + 'static,
+    Src::Err: Clone + 'static,
+    Sbj: Observer<Item = Src::Item, Err = Src::Err>
+      + @ctx::Observable<Item = Src::Item, Err = Src::Err>
+      + Clone
+      @ctx::local_only(+ 'static) @ctx::shared_only(+ Send + Sync + 'static),
+    F: FnMut() -> Sbj
+      @ctx::local_only(+ 'static) @ctx::shared_only(+ Send + Sync + 'static),
+    SD: @ctx::Scheduler + Clone
+      @ctx::local_only(+ 'static) @ctx::shared_only(+ Send + Sync + 'static),
+    U: SubscriptionLike
+      @ctx::local_only(+ 'static) @ctx::shared_only(+ Send + Sync + 'static)
+}
+
+pub struct ShareObserver<Sbj, R> {
+  subject: Sbj,
+  share: R,
+  generation: usize,
+}
+
+pub struct ShareSubscription<S, R> {
+  subscription: S,
+  share: R,
+  generation: usize,
+  closed: bool,
+}
+
+macro_rules! impl_share_observer {
+  ($rc: ident, $scheduler: ident $(, $bound: tt)*) => {
+    impl<Src, Sbj, F, SD, U> Observer
+      for ShareObserver<Sbj, $rc<InnerShare<Src, Sbj, F, SD, U>>>
+    where
+      Sbj: Observer,
+      U: SubscriptionLike,
+    {
+      type Item = Sbj::Item;
+      type Err = Sbj::Err;
+
+      #[inline]
+      fn next(&mut self, value: Self::Item) { self.subject.next(value) }
+
+      fn error(&mut self, err: Self::Err) {
+        self.subject.error(err);
+        let connection = {
+          let mut inner = self.share.rc_deref_mut();
+          let reset = inner.config.reset_on_error;
+          inner.terminate(self.generation, reset)
+        };
+        if let Some(mut connection) = connection {
+          connection.unsubscribe();
+        }
+      }
+
+      fn complete(&mut self) {
+        self.subject.complete();
+        let connection = {
+          let mut inner = self.share.rc_deref_mut();
+          let reset = inner.config.reset_on_complete;
+          inner.terminate(self.generation, reset)
+        };
+        if let Some(mut connection) = connection {
+          connection.unsubscribe();
+        }
+      }
+    }
+
+    impl<S, Src, Sbj, F, SD, U> SubscriptionLike
+      for ShareSubscription<S, $rc<InnerShare<Src, Sbj, F, SD, U>>>
+    where
+      S: SubscriptionLike,
+      SD: $scheduler + Clone,
+      U: SubscriptionLike,
+      $rc<InnerShare<Src, Sbj, F, SD, U>>: $($bound +)* 'static,
+    {
+      fn unsubscribe(&mut self) {
+        if self.closed {
+          return;
+        }
+        self.closed = true;
+        self.subscription.unsubscribe();
+        let release = self.share.rc_deref_mut().release(self.generation);
+        match release {
+          Release::Keep => {}
+          Release::Disconnect(connection) => {
+            if let Some(mut connection) = connection {
+              connection.unsubscribe();
+            }
+          }
+          Release::Grace(grace, generation) => {
+            let scheduler = self.share.rc_deref().config.scheduler.clone();
+            let timer = scheduler.schedule(
+              move |share: $rc<InnerShare<Src, Sbj, F, SD, U>>| {
+                let connection = share.rc_deref_mut().expire(generation);
+                if let Some(mut connection) = connection {
+                  connection.unsubscribe();
+                }
+              },
+              Some(grace),
+              self.share.clone(),
+            );
+            let mut inner = self.share.rc_deref_mut();
+            if inner.generation == generation && inner.count == 0 {
+              inner.reset_timer = Some(timer);
+            }
+          }
+        }
+      }
+
+      #[inline]
+      fn is_closed(&self) -> bool {
+        self.closed || self.subscription.is_closed()
+      }
+    }
+  };
+}
+
+impl_share_observer!(MutRc, LocalScheduler);
+impl_share_observer!(MutArc, SharedScheduler, Send, Sync);
+
+#[cfg(test)]
+mod test {
+  use crate::ops::share::ShareConfig;
+  use crate::prelude::*;
+  use crate::test_observer::TestObserver;
+  use crate::test_scheduler::TestScheduler;
+  use std::time::Duration;
+
+  // A source counting its subscriptions and unsubscriptions.
+  macro_rules! counted {
+    ($subject: ident, $subscribed: ident, $unsubscribed: ident) => {{
+      let subject = $subject.clone();
+      let subscribed = $subscribed.clone();
+      let unsubscribed = $unsubscribed.clone();
+      observable::defer(move || {
+        *subscribed.rc_deref_mut() += 1;
+        let unsubscribed = unsubscribed.clone();
+        subject
+          .clone()
+          .finalize(move || *unsubscribed.rc_deref_mut() += 1)
+      })
+    }};
+  }
+
+  #[test]
+  fn reset_on_ref_count_zero() {
+    let scheduler = TestScheduler::default();
+    let subject = LocalSubject::<i32, ()>::new();
+    let subscribed = MutRc::own(0);
+    let unsubscribed = MutRc::own(0);
+    let shared = counted!(subject, subscribed, unsubscribed)
+      .share_with(ShareConfig::new(LocalSubject::new, scheduler));
+
+    let mut s1 = shared.clone().actual_subscribe(TestObserver::new());
+    let mut s2 = shared.clone().actual_subscribe(TestObserver::new());
+    assert_eq!(*subscribed.rc_deref(), 1);
+    s1.unsubscribe();
+    assert_eq!(*unsubscribed.rc_deref(), 0);
+    s2.unsubscribe();
+    assert_eq!(*unsubscribed.rc_deref(), 1);
+
+    shared.actual_subscribe(TestObserver::new());
+    assert_eq!(*subscribed.rc_deref(), 2);
+  }
+
+  #[test]
+  fn never_reset_on_ref_count_zero() {
+    let scheduler = TestScheduler::default();
+    let mut subject = LocalSubject::<i32, ()>::new();
+    let subscribed = MutRc::own(0);
+    let unsubscribed = MutRc::own(0);
+    let shared =
+      counted!(subject, subscribed, unsubscribed).share_with(ShareConfig {
+        reset_on_ref_count_zero: false.into(),
+        ..ShareConfig::new(LocalSubject::new, scheduler)
+      });
+
+    shared
+      .clone()
+      .actual_subscribe(TestObserver::new())
+      .unsubscribe();
+    subject.next(1);
+    let observer = TestObserver::new();
+    shared.actual_subscribe(observer.clone());
+    subject.next(2);
+
+    assert_eq!(*subscribed.rc_deref(), 1);
+    assert_eq!(*unsubscribed.rc_deref(), 0);
+    observer.assert_values(&[2]);
+  }
+
+  #[test]
+  fn grace_period() {
+    let scheduler = TestScheduler::default();
+    let mut subject = LocalSubject::<i32, ()>::new();
+    let subscribed = MutRc::own(0);
+    let unsubscribed = MutRc::own(0);
+    let shared =
+      counted!(subject, subscribed, unsubscribed).share_with(ShareConfig {
+        reset_on_ref_count_zero: Duration::from_millis(100).into(),
+        ..ShareConfig::new(LocalSubject::new, scheduler.clone())
+      });
+
+    shared
+      .clone()
+      .actual_subscribe(TestObserver::new())
+      .unsubscribe();
+    scheduler.advance_by(Duration::from_millis(50));
+    let observer = TestObserver::new();
+    let mut subscription = shared.clone().actual_subscribe(observer.clone());
+    scheduler.advance_by(Duration::from_millis(100));
+    subject.next(1);
+    assert_eq!(*subscribed.rc_deref(), 1);
+    assert_eq!(*unsubscribed.rc_deref(), 0);
+    observer.assert_values(&[1]);
+
+    subscription.unsubscribe();
+    scheduler.advance_by(Duration::from_millis(99));
+    assert_eq!(*unsubscribed.rc_deref(), 0);
+    scheduler.advance_by(Duration::from_millis(1));
+    assert_eq!(*unsubscribed.rc_deref(), 1);
+
+    shared.actual_subscribe(TestObserver::new());
+    assert_eq!(*subscribed.rc_deref(), 2);
+  }
+
+  #[test]
+  fn reset_on_complete() {
+    let scheduler = TestScheduler::default();
+    let shared = observable::from_iter(0..2)
+      .share_with(ShareConfig::new(LocalSubject::new, scheduler.clone()));
+    let first = TestObserver::<_, ()>::new();
+    let second = TestObserver::new();
+    shared.clone().actual_subscribe(first.clone());
+    shared.actual_subscribe(second.clone());
+
+    first.assert_values(&[0, 1]).assert_complete();
+    second.assert_values(&[0, 1]).assert_complete();
+
+    let kept = observable::from_iter(0..2).share_with(ShareConfig {
+      reset_on_complete: false,
+      ..ShareConfig::new(LocalSubject::new, scheduler)
+    });
+    let late = TestObserver::<_, ()>::new();
+    kept.clone().actual_subscribe(TestObserver::new());
+    kept.actual_subscribe(late.clone());
+    late.assert_values(&[]);
+  }
+
+  #[test]
+  fn reset_on_error() {
+    let scheduler = TestScheduler::default();
+    let subscribed = MutRc::own(0);
+    let c_subscribed = subscribed.clone();
+    let shared = observable::defer(move || {
+      *c_subscribed.rc_deref_mut() += 1;
+      observable::throw("oops")
+    })
+    .share_with(ShareConfig {
+      reset_on_complete: false,
+      ..ShareConfig::new(LocalSubject::new, scheduler)
+    });
+
+    let observer = TestObserver::new();
+    shared.clone().actual_subscribe(TestObserver::new());
+    shared.actual_subscribe(observer.clone());
+
+    assert_eq!(*subscribed.rc_deref(), 2);
+    observer.assert_error_matches(|e| *e == "oops");
+  }
+
+  #[cfg(not(target_arch = "wasm32"))]
+  #[test]
+  fn grace_period_shared() {
+    let scheduler = TestScheduler::default();
+    let mut subject = SharedSubject::new();
+    let shared = subject.clone().share_with(ShareConfig {
+      reset_on_ref_count_zero: Duration::from_millis(100).into(),
+      ..ShareConfig::new(SharedSubject::new, scheduler.clone())
+    });
+
+    let observer = TestObserver::<i32, ()>::new();
+    shared
+      .clone()
+      .into_shared()
+      .actual_subscribe(TestObserver::new())
+      .unsubscribe();
+    scheduler.advance_by(Duration::from_millis(50));
+    shared.into_shared().actual_subscribe(observer.clone());
+    scheduler.advance_by(Duration::from_millis(100));
+    subject.next(1);
+
+    observer.assert_values(&[1]);
+    assert_eq!(subject.teardown_size(), 1);
+  }
+}
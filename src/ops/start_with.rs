@@ -40,7 +40,7 @@ impl_local_shared_both! {
 mod test {
   use crate::of_sequence;
   use crate::prelude::*;
-  use crate::test_scheduler::ManualScheduler;
+  use crate::test_scheduler::TestScheduler;
   use std::cell::RefCell;
   use std::rc::Rc;
   use std::time::Duration;
@@ -77,7 +77,7 @@ mod test {
 
   #[test]
   fn should_start_on_subscription() {
-    let scheduler = ManualScheduler::now();
+    let scheduler = TestScheduler::default();
     let values = Rc::new(RefCell::new(vec![]));
     let interval =
       observable::interval(Duration::from_millis(100), scheduler.clone());
@@ -89,7 +89,7 @@ mod test {
         .subscribe(move |v| values.borrow_mut().push(v));
     }
 
-    scheduler.advance_and_run(Duration::from_millis(10), 1);
+    scheduler.advance_by(Duration::from_millis(10));
     assert_eq!(values.borrow().len(), 1);
     assert_eq!(values.borrow().as_ref(), vec![0]);
   }
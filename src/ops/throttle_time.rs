@@ -124,13 +124,13 @@ impl_observer!(MutArc, SharedScheduler, Send);
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::test_scheduler::ManualScheduler;
+  use crate::test_scheduler::TestScheduler;
 
   #[test]
   fn smoke() {
     let x = MutRc::own(vec![]);
     let x_c = x.clone();
-    let scheduler = ManualScheduler::now();
+    let scheduler = TestScheduler::default();
 
     let interval =
       observable::interval(Duration::from_millis(5), scheduler.clone());
@@ -146,14 +146,14 @@ mod tests {
     // tailing throttle
 
     let mut sub = throttle_subscribe(ThrottleEdge::Tailing);
-    scheduler.advance_and_run(Duration::from_millis(1), 25);
+    scheduler.advance_by(Duration::from_millis(25));
     sub.unsubscribe();
     assert_eq!(&*x_c.rc_deref(), &[2, 4]);
 
     // leading throttle
     x_c.rc_deref_mut().clear();
     throttle_subscribe(ThrottleEdge::Leading);
-    scheduler.advance_and_run(Duration::from_millis(1), 25);
+    scheduler.advance_by(Duration::from_millis(25));
     assert_eq!(&*x_c.rc_deref(), &[0, 3]);
   }
 
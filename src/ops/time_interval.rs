@@ -0,0 +1,104 @@
+use crate::scheduler::Instant;
+use crate::{impl_helper::*, impl_local_shared_both, prelude::*};
+use std::time::Duration;
+
+#[derive(Clone)]
+pub struct TimeIntervalOp<S, SD> {
+  pub(crate) source: S,
+  pub(crate) scheduler: SD,
+}
+
+impl<S: Observable, SD> Observable for TimeIntervalOp<S, SD> {
+  type Item = (Duration, S::Item);
+  type Err = S::Err;
+}
+
+impl_local_shared_both! {
+  impl<S, SD> TimeIntervalOp<S, SD>;
+  type Unsub = S::Unsub;
+  macro method($self: ident, $observer: ident, $ctx: ident) {
+    let scheduler = $self.scheduler;
+    let last = scheduler.now();
+    $self.source.actual_subscribe(TimeIntervalObserver {
+      observer: $observer,
+      now: move || scheduler.now(),
+      last,
+    })
+  }
+  where
+    S: @ctx::Observable,
+    S::Item: @ctx::local_only('o) @ctx::shared_only('static),
+    SD: @ctx::Scheduler
+      + @ctx::local_only('o) @ctx::shared_only(Send + Sync + 'static)
+}
+
+struct TimeIntervalObserver<O, F> {
+  observer: O,
+  now: F,
+  last: Instant,
+}
+
+impl<Item, Err, O, F> Observer for TimeIntervalObserver<O, F>
+where
+  O: Observer<Item = (Duration, Item), Err = Err>,
+  F: Fn() -> Instant,
+{
+  type Item = Item;
+  type Err = Err;
+  fn next(&mut self, value: Item) {
+    let now = (self.now)();
+    let interval = now.saturating_duration_since(self.last);
+    self.last = now;
+    self.observer.next((interval, value))
+  }
+
+  fn error(&mut self, err: Self::Err) { self.observer.error(err) }
+
+  fn complete(&mut self) { self.observer.complete() }
+}
+
+#[cfg(test)]
+mod test {
+  use crate::prelude::*;
+  use crate::test_scheduler::TestScheduler;
+  use std::time::Duration;
+
+  #[test]
+  fn virtual_time() {
+    let scheduler = TestScheduler::default();
+    let mut subject = LocalSubject::new();
+    let intervals = MutRc::own(vec![]);
+    let c_intervals = intervals.clone();
+    subject
+      .clone()
+      .time_interval(scheduler.clone())
+      .subscribe(move |v| c_intervals.rc_deref_mut().push(v));
+
+    scheduler.advance_by(Duration::from_millis(3));
+    subject.next(1);
+    scheduler.advance_by(Duration::from_millis(5));
+    subject.next(2);
+    subject.next(3);
+
+    assert_eq!(
+      &*intervals.rc_deref(),
+      &[
+        (Duration::from_millis(3), 1),
+        (Duration::from_millis(5), 2),
+        (Duration::from_millis(0), 3)
+      ]
+    );
+  }
+
+  #[cfg(not(target_arch = "wasm32"))]
+  #[test]
+  fn fork_and_shared() {
+    use futures::executor::ThreadPool;
+    let scheduler = ThreadPool::new().unwrap();
+    observable::from_iter(0..10)
+      .time_interval(scheduler)
+      .into_shared()
+      .into_shared()
+      .subscribe(|_| {});
+  }
+}
@@ -0,0 +1,96 @@
+use crate::scheduler::Instant;
+use crate::{impl_helper::*, impl_local_shared_both, prelude::*};
+
+#[derive(Clone)]
+pub struct TimestampOp<S, SD> {
+  pub(crate) source: S,
+  pub(crate) scheduler: SD,
+}
+
+impl<S: Observable, SD> Observable for TimestampOp<S, SD> {
+  type Item = (Instant, S::Item);
+  type Err = S::Err;
+}
+
+impl_local_shared_both! {
+  impl<S, SD> TimestampOp<S, SD>;
+  type Unsub = S::Unsub;
+  macro method($self: ident, $observer: ident, $ctx: ident) {
+    let scheduler = $self.scheduler;
+    $self.source.actual_subscribe(TimestampObserver {
+      observer: $observer,
+      now: move || scheduler.now(),
+    })
+  }
+  where
+    S: @ctx::Observable,
+    S::Item: @ctx::local_only('o) @ctx::shared_only('static),
+    SD: @ctx::Scheduler
+      + @ctx::local_only('o) @ctx::shared_only(Send + Sync + 'static)
+}
+
+struct TimestampObserver<O, F> {
+  observer: O,
+  now: F,
+}
+
+impl<Item, Err, O, F> Observer for TimestampObserver<O, F>
+where
+  O: Observer<Item = (Instant, Item), Err = Err>,
+  F: Fn() -> Instant,
+{
+  type Item = Item;
+  type Err = Err;
+  fn next(&mut self, value: Item) { self.observer.next(((self.now)(), value)) }
+
+  fn error(&mut self, err: Self::Err) { self.observer.error(err) }
+
+  fn complete(&mut self) { self.observer.complete() }
+}
+
+#[cfg(test)]
+mod test {
+  use crate::prelude::*;
+  use crate::test_scheduler::TestScheduler;
+  use std::time::Duration;
+
+  #[test]
+  fn virtual_time() {
+    let scheduler = TestScheduler::default();
+    let start = scheduler.now();
+    let mut subject = LocalSubject::new();
+    let stamps = MutRc::own(vec![]);
+    let c_stamps = stamps.clone();
+    subject
+      .clone()
+      .timestamp(scheduler.clone())
+      .subscribe(move |v| c_stamps.rc_deref_mut().push(v));
+
+    subject.next(1);
+    scheduler.advance_by(Duration::from_millis(5));
+    subject.next(2);
+    scheduler.advance_by(Duration::from_millis(10));
+    subject.next(3);
+
+    assert_eq!(
+      &*stamps.rc_deref(),
+      &[
+        (start, 1),
+        (start + Duration::from_millis(5), 2),
+        (start + Duration::from_millis(15), 3)
+      ]
+    );
+  }
+
+  #[cfg(not(target_arch = "wasm32"))]
+  #[test]
+  fn fork_and_shared() {
+    use futures::executor::ThreadPool;
+    let scheduler = ThreadPool::new().unwrap();
+    observable::from_iter(0..10)
+      .timestamp(scheduler)
+      .into_shared()
+      .into_shared()
+      .subscribe(|_| {});
+  }
+}
@@ -0,0 +1,140 @@
+use crate::{impl_helper::*, impl_local_shared_both, prelude::*};
+
+#[derive(Clone)]
+pub struct UnsubscribeOnOp<S, SD> {
+  pub(crate) source: S,
+  pub(crate) scheduler: SD,
+}
+
+impl<S: Observable, SD> Observable for UnsubscribeOnOp<S, SD> {
+  type Item = S::Item;
+  type Err = S::Err;
+}
+
+impl_local_shared_both! {
+  impl<S, SD> UnsubscribeOnOp<S, SD>;
+  type Unsub = UnsubscribeOnSubscription<@ctx::Rc<S::Unsub>, SD>;
+  macro method($self: ident, $observer: ident, $ctx: ident) {
+    let subscription = $self.source.actual_subscribe($observer);
+    UnsubscribeOnSubscription {
+      subscription: $ctx::Rc::own(subscription),
+      scheduler: $self.scheduler,
+      unsubscribed: false,
+    }
+  }
+  where
+    @ctx::local_only('o: 'static,)
+    S: @ctx::Observable,
+    S::Unsub: 'static,
+    SD: @ctx::Scheduler @ctx::shared_only(+ Send + Sync) + 'static
+}
+
+/// The subscription returned by `unsubscribe_on`, it dispatches the
+/// unsubscribe of the upstream subscription to the scheduler.
+pub struct UnsubscribeOnSubscription<U, SD> {
+  subscription: U,
+  scheduler: SD,
+  unsubscribed: bool,
+}
+
+macro_rules! impl_subscription {
+  ($rc: ident, $scheduler_bound: ident $(,$send: ident, $sync: ident)?) => {
+    impl<U, SD> SubscriptionLike for UnsubscribeOnSubscription<$rc<U>, SD>
+    where
+      U: SubscriptionLike $(+ $send + $sync)? + 'static,
+      SD: $scheduler_bound,
+    {
+      fn unsubscribe(&mut self) {
+        if !self.unsubscribed {
+          self.unsubscribed = true;
+          self.scheduler.schedule(
+            |mut subscription: $rc<U>| subscription.unsubscribe(),
+            None,
+            self.subscription.clone(),
+          );
+        }
+      }
+
+      #[inline]
+      fn is_closed(&self) -> bool {
+        self.unsubscribed || self.subscription.is_closed()
+      }
+    }
+  };
+}
+
+impl_subscription!(MutRc, LocalScheduler);
+#[cfg(not(all(target_arch = "wasm32")))]
+impl_subscription!(MutArc, SharedScheduler, Send, Sync);
+
+#[cfg(test)]
+mod test {
+  use crate::prelude::*;
+  use futures::executor::LocalPool;
+  use std::cell::Cell;
+  use std::rc::Rc;
+
+  #[test]
+  fn local() {
+    let mut local = LocalPool::new();
+    let finalized = Rc::new(Cell::new(false));
+    let c_finalized = finalized.clone();
+    let mut subscription = observable::never()
+      .finalize(move || c_finalized.set(true))
+      .unsubscribe_on(local.spawner())
+      .subscribe(|_| {});
+
+    subscription.unsubscribe();
+    assert!(subscription.is_closed());
+    assert!(!finalized.get());
+
+    local.run();
+    assert!(finalized.get());
+  }
+
+  #[test]
+  fn emit_until_unsubscribe_dispatched() {
+    let mut local = LocalPool::new();
+    let mut subject = LocalSubject::new();
+    let values = Rc::new(Cell::new(0));
+    let c_values = values.clone();
+    let mut subscription = subject
+      .clone()
+      .unsubscribe_on(local.spawner())
+      .subscribe(move |v| c_values.set(v));
+
+    subject.next(1);
+    subscription.unsubscribe();
+    subject.next(2);
+    assert_eq!(values.get(), 2);
+
+    local.run();
+    subject.next(3);
+    assert_eq!(values.get(), 2);
+  }
+
+  #[cfg(not(target_arch = "wasm32"))]
+  #[test]
+  fn thread_pool() {
+    use futures::executor::ThreadPool;
+    use std::sync::{Mutex, mpsc};
+    use std::thread;
+    use std::time::Duration;
+
+    let pool = ThreadPool::new().unwrap();
+    let (sender, receiver) = mpsc::channel();
+    let sender = Mutex::new(sender);
+    observable::never()
+      .into_shared()
+      .finalize(move || {
+        let _ = sender.lock().unwrap().send(thread::current().id());
+      })
+      .unsubscribe_on(pool)
+      .into_shared()
+      .subscribe(|_| {})
+      .unsubscribe();
+
+    let id = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_ne!(id, thread::current().id());
+  }
+}
@@ -46,12 +46,12 @@ pub struct MutArc<T>(Arc<Mutex<T>>);
 #[derive(Default)]
 pub struct BufferedMutRc<T, Item, Err> {
   inner: MutRc<T>,
-  buffer: MutRc<Vec<ObserverTrigger<Item, Err>>>,
+  buffer: MutRc<Vec<Notification<Item, Err>>>,
 }
 #[derive(Default)]
 pub struct BufferedMutArc<T, Item, Err> {
   inner: MutArc<T>,
-  buffer: MutArc<Vec<ObserverTrigger<Item, Err>>>,
+  buffer: MutArc<Vec<Notification<Item, Err>>>,
 }
 
 impl<T> MutArc<T> {
@@ -256,16 +256,12 @@ where
   O: DerefMut,
   O::Target: Observer<Item = Item, Err = Err>,
   B: RcDerefMut<Target<'a> = T> + 'a,
-  T: DerefMut<Target = Vec<ObserverTrigger<Item, Err>>>,
+  T: DerefMut<Target = Vec<Notification<Item, Err>>>,
 {
   loop {
     let v = b.rc_deref_mut().pop();
     if let Some(to_emit) = v {
-      match to_emit {
-        ObserverTrigger::Item(v) => observer.next(v),
-        ObserverTrigger::Err(err) => observer.error(err),
-        ObserverTrigger::Complete => observer.complete(),
-      }
+      to_emit.accept(&mut *observer);
     } else {
       break;
     }
@@ -288,7 +284,7 @@ macro_rules! observer_impl_for_brc {
           self
             .buffer
             .rc_deref_mut()
-            .push(ObserverTrigger::Item(value));
+            .push(Notification::Next(value));
         }
       }
       fn error(&mut self, err: Self::Err) {
@@ -296,7 +292,7 @@ macro_rules! observer_impl_for_brc {
           inner.error(err);
           emit_buffer(inner, &self.buffer);
         } else {
-          self.buffer.rc_deref_mut().push(ObserverTrigger::Err(err));
+          self.buffer.rc_deref_mut().push(Notification::Error(err));
         }
       }
       fn complete(&mut self) {
@@ -304,7 +300,7 @@ macro_rules! observer_impl_for_brc {
           inner.complete();
           emit_buffer(inner, &self.buffer);
         } else {
-          self.buffer.rc_deref_mut().push(ObserverTrigger::Complete);
+          self.buffer.rc_deref_mut().push(Notification::Complete);
         }
       }
     }
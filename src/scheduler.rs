@@ -1,14 +1,26 @@
 use crate::prelude::*;
 use async_std::prelude::FutureExt as AsyncFutureExt;
-use futures::future::{lazy, AbortHandle, FutureExt};
+use futures::future::{AbortHandle, FutureExt, lazy};
 use std::future::Future;
 
-use futures::StreamExt;
 use std::sync::{Arc, RwLock};
 use std::time::Duration;
 
 pub(crate) use fluvio_wasm_timer::Instant;
 
+#[cfg(not(target_arch = "wasm32"))]
+mod event_loop;
+#[cfg(not(target_arch = "wasm32"))]
+pub use event_loop::EventLoopScheduler;
+#[cfg(not(target_arch = "wasm32"))]
+mod immediate;
+#[cfg(not(target_arch = "wasm32"))]
+pub use immediate::ImmediateScheduler;
+#[cfg(not(target_arch = "wasm32"))]
+mod trampoline;
+#[cfg(not(target_arch = "wasm32"))]
+pub use trampoline::TrampolineScheduler;
+
 pub fn task_future<T>(
   task: impl FnOnce(T) + 'static,
   state: T,
@@ -26,6 +38,11 @@ pub trait SharedScheduler {
   where
     Fut: Future<Output = ()> + Send + 'static;
 
+  /// Returns the current time of this scheduler, time-based operators read
+  /// the time from it. A virtual-time scheduler can override it.
+  #[inline]
+  fn now(&self) -> Instant { Instant::now() }
+
   fn schedule<T: Send + 'static>(
     &self,
     task: impl FnOnce(T) + Send + 'static,
@@ -42,8 +59,10 @@ pub trait SharedScheduler {
     task: impl FnMut(usize) + Send + 'static,
     time_between: Duration,
     at: Option<Instant>,
+    policy: IntervalPolicy,
   ) -> SpawnHandle {
-    let (f, handle) = repeating_future(task, time_between, at);
+    let (f, handle) =
+      repeating_future(task, time_between, at, self.now(), policy);
     self.spawn(f.map(|_| ()));
     handle
   }
@@ -54,6 +73,11 @@ pub trait LocalScheduler {
   where
     Fut: Future<Output = ()> + 'static;
 
+  /// Returns the current time of this scheduler, time-based operators read
+  /// the time from it. A virtual-time scheduler can override it.
+  #[inline]
+  fn now(&self) -> Instant { Instant::now() }
+
   fn schedule<T: 'static>(
     &self,
     task: impl FnOnce(T) + 'static,
@@ -70,8 +94,10 @@ pub trait LocalScheduler {
     task: impl FnMut(usize) + 'static,
     time_between: Duration,
     at: Option<Instant>,
+    policy: IntervalPolicy,
   ) -> SpawnHandle {
-    let (f, handle) = repeating_future(task, time_between, at);
+    let (f, handle) =
+      repeating_future(task, time_between, at, self.now(), policy);
     self.spawn(f.map(|_| ()));
     handle
   }
@@ -106,13 +132,57 @@ impl SubscriptionLike for SpawnHandle {
   fn is_closed(&self) -> bool { *self.is_closed.read().unwrap() }
 }
 
+/// How a repeating task catches up with the ticks it missed, when it ran late
+/// because the scheduler was busy or the process was paused. The deadlines of
+/// the ticks are computed from the first one, so they don't drift.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IntervalPolicy {
+  /// Runs the missed ticks immediately one after another, so the count of
+  /// ticks is the same as if none was missed.
+  Burst,
+  /// Runs the next tick a full period after a tick which ran so late that the
+  /// next deadline already passed, shifting the following deadlines, so two
+  /// ticks are never closer than the period. A tick running late by less than
+  /// a period keeps the cadence.
+  #[default]
+  Delay,
+  /// Drops the missed ticks and runs the next tick at the next deadline of
+  /// the original cadence.
+  Skip,
+}
+
+impl IntervalPolicy {
+  /// Returns the deadline of the tick following the one due at `deadline`,
+  /// that ran at `now`.
+  pub fn next_deadline(
+    self,
+    deadline: Instant,
+    period: Duration,
+    now: Instant,
+  ) -> Instant {
+    let next = deadline + period;
+    match self {
+      IntervalPolicy::Burst => next,
+      IntervalPolicy::Delay if next <= now => now + period,
+      IntervalPolicy::Skip if next <= now && !period.is_zero() => {
+        // How late `now` is on the original cadence, computed without
+        // counting the missed ticks, which can be arbitrarily many.
+        let behind = (now - deadline).as_nanos() % period.as_nanos();
+        now + period
+          - Duration::from_nanos(u64::try_from(behind).unwrap_or(u64::MAX))
+      }
+      _ => next,
+    }
+  }
+}
+
 #[cfg(feature = "futures-scheduler")]
 mod futures_scheduler {
   use crate::scheduler::LocalScheduler;
   #[cfg(not(target_arch = "wasm32"))]
   use crate::scheduler::SharedScheduler;
   use futures::{
-    executor::LocalSpawner, task::LocalSpawnExt, Future, FutureExt,
+    Future, FutureExt, executor::LocalSpawner, task::LocalSpawnExt,
   };
   #[cfg(not(target_arch = "wasm32"))]
   use futures::{executor::ThreadPool, task::SpawnExt};
@@ -137,12 +207,88 @@ mod futures_scheduler {
   }
 }
 
+#[cfg(feature = "async-std-scheduler")]
+pub use async_std_scheduler::AsyncStdScheduler;
+
+#[cfg(feature = "async-std-scheduler")]
+mod async_std_scheduler {
+  use crate::scheduler::LocalScheduler;
+  #[cfg(not(target_arch = "wasm32"))]
+  use crate::scheduler::SharedScheduler;
+  use futures::Future;
+
+  /// A scheduler spawning tasks onto the executor of `async-std`. As a
+  /// `SharedScheduler` it spawns by `async_std::task::spawn`, as a
+  /// `LocalScheduler` by `async_std::task::spawn_local`, which runs the task
+  /// on the current thread.
+  #[derive(Clone, Copy, Default)]
+  pub struct AsyncStdScheduler;
+
+  #[cfg(not(target_arch = "wasm32"))]
+  impl SharedScheduler for AsyncStdScheduler {
+    fn spawn<Fut>(&self, future: Fut)
+    where
+      Fut: Future<Output = ()> + Send + 'static,
+    {
+      async_std::task::spawn(future);
+    }
+  }
+
+  impl LocalScheduler for AsyncStdScheduler {
+    fn spawn<Fut>(&self, future: Fut)
+    where
+      Fut: Future<Output = ()> + 'static,
+    {
+      async_std::task::spawn_local(future);
+    }
+  }
+
+  #[cfg(test)]
+  mod test {
+    use super::*;
+    use crate::prelude::*;
+    use std::time::{Duration, Instant};
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn shared() {
+      use std::sync::{Arc, Mutex};
+      let sum = Arc::new(Mutex::new(0));
+      let c_sum = sum.clone();
+      let stamp = Instant::now();
+      observable::from_iter(1..=3)
+        .delay(Duration::from_millis(10), AsyncStdScheduler)
+        .observe_on(AsyncStdScheduler)
+        .into_shared()
+        .subscribe_blocking(move |v| *c_sum.lock().unwrap() += v);
+
+      assert!(stamp.elapsed() >= Duration::from_millis(10));
+      assert_eq!(*sum.lock().unwrap(), 6);
+    }
+
+    #[test]
+    fn local() {
+      let ticks = MutRc::own(vec![]);
+      let c_ticks = ticks.clone();
+      async_std::task::block_on(async move {
+        observable::interval(Duration::from_millis(1), AsyncStdScheduler)
+          .take(5)
+          .subscribe(move |v| c_ticks.rc_deref_mut().push(v));
+        async_std::task::sleep(Duration::from_millis(50)).await;
+      });
+
+      assert_eq!(&*ticks.rc_deref(), &[0, 1, 2, 3, 4]);
+    }
+  }
+}
+
 fn repeating_future(
-  task: impl FnMut(usize) + 'static,
+  mut task: impl FnMut(usize) + 'static,
   time_between: Duration,
   at: Option<Instant>,
+  now: Instant,
+  policy: IntervalPolicy,
 ) -> (impl Future<Output = ()>, SpawnHandle) {
-  let now = Instant::now();
   let delay = at.map(|inst| {
     if inst > now {
       inst - now
@@ -150,35 +296,90 @@ fn repeating_future(
       Duration::from_micros(0)
     }
   });
-  let future = to_interval(task, time_between, delay.unwrap_or(time_between));
+  // The clock of the scheduler, advancing as the future sleeps.
+  let start = Instant::now();
+  let clock = move || now + start.elapsed();
+  let mut deadline = now + delay.unwrap_or(time_between);
+  let future = async move {
+    let mut number = 0;
+    loop {
+      let now = clock();
+      if deadline > now {
+        sleep(deadline - now).await;
+      } else {
+        // Still yields when late, so the task can be aborted while bursting.
+        async_std::task::yield_now().await;
+      }
+      task(number);
+      number += 1;
+      deadline = policy.next_deadline(deadline, time_between, clock());
+    }
+  };
   let (fut, handle) = futures::future::abortable(future);
   (fut.map(|_| ()), SpawnHandle::new(handle))
 }
 
-fn to_interval(
-  mut task: impl FnMut(usize) + 'static,
-  interval_duration: Duration,
-  delay: Duration,
-) -> impl Future<Output = ()> {
-  let mut number = 0;
+#[cfg(not(target_arch = "wasm32"))]
+async fn sleep(dur: Duration) { async_std::task::sleep(dur).await }
 
-  futures::future::ready(())
-    .then(move |_| {
-      task(number);
-      async_std::stream::interval(interval_duration).for_each(move |_| {
-        number += 1;
-        task(number);
-        futures::future::ready(())
-      })
-    })
-    .delay(delay)
+#[cfg(target_arch = "wasm32")]
+async fn sleep(dur: Duration) {
+  let _ = fluvio_wasm_timer::Delay::new(dur).await;
 }
 
+#[cfg(feature = "tokio-scheduler")]
+pub use tokio_scheduler::TokioLocalSpawner;
+
 #[cfg(feature = "tokio-scheduler")]
 mod tokio_scheduler {
   use super::*;
+  use std::rc::Rc;
   use std::sync::Arc;
-  use tokio::runtime::Runtime;
+  use tokio::runtime::{Handle, Runtime};
+  use tokio::task::LocalSet;
+
+  /// A local scheduler spawning tasks onto the `LocalSet` the current thread
+  /// is running, for example in a current-thread runtime driven by
+  /// `LocalSet::block_on` or `LocalSet::run_until`. Spawning outside a
+  /// `LocalSet` panics.
+  #[derive(Clone, Copy, Default)]
+  pub struct TokioLocalSpawner;
+
+  impl LocalScheduler for TokioLocalSpawner {
+    fn spawn<Fut>(&self, future: Fut)
+    where
+      Fut: Future<Output = ()> + 'static,
+    {
+      tokio::task::spawn_local(future);
+    }
+  }
+
+  impl LocalScheduler for LocalSet {
+    fn spawn<Fut>(&self, future: Fut)
+    where
+      Fut: Future<Output = ()> + 'static,
+    {
+      LocalSet::spawn_local(self, future);
+    }
+  }
+
+  impl LocalScheduler for Rc<LocalSet> {
+    fn spawn<Fut>(&self, future: Fut)
+    where
+      Fut: Future<Output = ()> + 'static,
+    {
+      LocalSet::spawn_local(self, future);
+    }
+  }
+
+  impl SharedScheduler for Handle {
+    fn spawn<Fut>(&self, future: Fut)
+    where
+      Fut: Future<Output = ()> + Send + 'static,
+    {
+      Handle::spawn(self, future);
+    }
+  }
 
   impl SharedScheduler for Runtime {
     fn spawn<Fut>(&self, future: Fut)
@@ -252,6 +453,60 @@ mod test {
     })
   }
 
+  #[test]
+  fn tokio_local_set() {
+    use std::rc::Rc;
+    use std::time::Duration;
+    use tokio::{runtime, task::LocalSet};
+
+    let rt = runtime::Builder::new_current_thread().build().unwrap();
+    let local = Rc::new(LocalSet::new());
+    let ticks = MutRc::own(vec![]);
+    let c_ticks = ticks.clone();
+    observable::interval(Duration::from_millis(1), local.clone())
+      .take(3)
+      .subscribe(move |v| c_ticks.rc_deref_mut().push(v));
+    let c_ticks = ticks.clone();
+    observable::of(3)
+      .delay(Duration::from_millis(10), local.clone())
+      .subscribe(move |v| c_ticks.rc_deref_mut().push(v));
+
+    rt.block_on(
+      local.run_until(async_std::task::sleep(Duration::from_millis(50))),
+    );
+    assert_eq!(&*ticks.rc_deref(), &[0, 1, 2, 3]);
+  }
+
+  #[test]
+  fn tokio_local_spawner() {
+    use std::time::Duration;
+    use tokio::{runtime, task::LocalSet};
+
+    let rt = runtime::Builder::new_current_thread().build().unwrap();
+    let value = MutRc::own(0);
+    let c_value = value.clone();
+    LocalSet::new().block_on(&rt, async move {
+      observable::timer(1, Duration::from_millis(1), TokioLocalSpawner)
+        .subscribe(move |v| *c_value.rc_deref_mut() = v);
+      async_std::task::sleep(Duration::from_millis(20)).await;
+    });
+    assert_eq!(*value.rc_deref(), 1);
+  }
+
+  #[test]
+  fn tokio_handle() {
+    use tokio::runtime;
+    let rt = runtime::Runtime::new().unwrap();
+    let sum = Arc::new(Mutex::new(0));
+    let c_sum = sum.clone();
+    observable::from_iter(1..=3)
+      .observe_on(rt.handle().clone())
+      .into_shared()
+      .subscribe_blocking(move |v| *c_sum.lock().unwrap() += v);
+
+    assert_eq!(*sum.lock().unwrap(), 6);
+  }
+
   #[test]
   fn bench_tokio_basic() { do_bench_tokio_basic(); }
 
@@ -303,40 +558,9 @@ pub struct LocalSpawner;
 
 #[cfg(all(target_arch = "wasm32", feature = "wasm-scheduler"))]
 mod wasm_scheduler {
-  use super::{Duration, Instant, SpawnHandle, StreamExt};
   use crate::scheduler::{LocalScheduler, LocalSpawner};
   use futures::{Future, FutureExt};
 
-  #[cfg(all(target_arch = "wasm32"))]
-  fn to_interval(
-    mut task: impl FnMut(usize) + 'static,
-    interval_duration: Duration,
-    at: Option<Instant>,
-  ) -> impl Future<Output = ()> {
-    let mut number = 0;
-    let at = at.unwrap_or(Instant::now() + interval_duration);
-
-    futures::future::ready(()).then(move |_| {
-      fluvio_wasm_timer::Interval::new_at(at, interval_duration).for_each(
-        move |_| {
-          number += 1;
-          task(number);
-          futures::future::ready(())
-        },
-      )
-    })
-  }
-
-  fn repeating_future(
-    task: impl FnMut(usize) + 'static,
-    time_between: Duration,
-    at: Option<Instant>,
-  ) -> (impl Future<Output = ()>, SpawnHandle) {
-    let future = to_interval(task, time_between, at);
-    let (fut, handle) = futures::future::abortable(future);
-    (fut.map(|_| ()), SpawnHandle::new(handle))
-  }
-
   impl LocalScheduler for LocalSpawner {
     fn spawn<Fut>(&self, future: Fut)
     where
@@ -344,17 +568,6 @@ mod wasm_scheduler {
     {
       wasm_bindgen_futures::spawn_local(future.map(|_| ()));
     }
-
-    fn schedule_repeating(
-      &self,
-      task: impl FnMut(usize) + 'static,
-      time_between: Duration,
-      at: Option<Instant>,
-    ) -> SpawnHandle {
-      let (f, handle) = repeating_future(task, time_between, at);
-      self.spawn(f.map(|_| ()));
-      handle
-    }
   }
 }
 
@@ -0,0 +1,417 @@
+use crate::prelude::*;
+use crate::scheduler::Instant;
+use futures::future::{AbortHandle, BoxFuture};
+use futures::task::{ArcWake, waker_ref};
+use std::any::Any;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::Context;
+use std::thread::{JoinHandle, ThreadId};
+use std::time::Duration;
+
+/// A shared scheduler owning one worker thread, all the tasks scheduled on it
+/// run serially on that thread, in the order of their deadlines.
+///
+/// It gives the thread affinity of a UI or IO thread to the tasks without a
+/// thread pool. The scheduler is a cheap handle, its clones schedule on the
+/// same thread. The worker thread runs until [`shutdown`] is called, the
+/// tasks still pending then are dropped without running. Use [`join`] to wait
+/// for the worker thread to exit.
+///
+/// A task which panics is dropped, and also cancelled if it's repeating, the
+/// worker thread keeps running the other tasks. [`join`] resumes the panic.
+///
+/// ```rust
+/// # use rxrust::prelude::*;
+/// use std::sync::mpsc::channel;
+/// use std::time::Duration;
+///
+/// let scheduler = EventLoopScheduler::new();
+/// let (tx, rx) = channel();
+/// observable::interval(Duration::from_millis(1), scheduler.clone())
+///   .take(3)
+///   .into_shared()
+///   .subscribe(move |v| tx.send(v).unwrap());
+///
+/// assert_eq!(rx.iter().collect::<Vec<_>>(), vec![0, 1, 2]);
+/// scheduler.shutdown();
+/// scheduler.join();
+/// ```
+///
+/// [`shutdown`]: EventLoopScheduler::shutdown
+/// [`join`]: EventLoopScheduler::join
+#[derive(Clone)]
+pub struct EventLoopScheduler {
+  inner: Arc<Inner>,
+}
+
+struct Inner {
+  state: Mutex<State>,
+  cvar: Condvar,
+  thread: Mutex<Option<JoinHandle<()>>>,
+  thread_id: ThreadId,
+  /// The panic of the first task which panicked.
+  panic: Mutex<Option<Box<dyn Any + Send>>>,
+}
+
+#[derive(Default)]
+struct State {
+  shutdown: bool,
+  seq: u64,
+  queue: BinaryHeap<Reverse<Entry>>,
+}
+
+struct Entry {
+  due: Instant,
+  seq: u64,
+  period: Option<(Duration, IntervalPolicy)>,
+  invokes: usize,
+  cancel: SpawnHandle,
+  task: Box<dyn FnMut(usize) + Send>,
+}
+
+impl PartialEq for Entry {
+  fn eq(&self, other: &Self) -> bool { self.cmp(other) == Ordering::Equal }
+}
+
+impl Eq for Entry {}
+
+impl PartialOrd for Entry {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Ord for Entry {
+  fn cmp(&self, other: &Self) -> Ordering {
+    (self.due, self.seq).cmp(&(other.due, other.seq))
+  }
+}
+
+impl Default for EventLoopScheduler {
+  fn default() -> Self { Self::new() }
+}
+
+impl EventLoopScheduler {
+  /// Creates a scheduler and starts its worker thread.
+  pub fn new() -> Self {
+    // The worker receives the state once its thread id is known.
+    let (tx, rx) = std::sync::mpsc::channel::<Arc<Inner>>();
+    let thread = std::thread::Builder::new()
+      .name("rxrust-event-loop".into())
+      .spawn(move || {
+        if let Ok(inner) = rx.recv() {
+          inner.run()
+        }
+      })
+      .expect("failed to spawn the event loop thread.");
+    let inner = Arc::new(Inner {
+      state: Mutex::new(State::default()),
+      cvar: Condvar::new(),
+      thread_id: thread.thread().id(),
+      thread: Mutex::new(Some(thread)),
+      panic: Mutex::new(None),
+    });
+    tx.send(inner.clone()).unwrap();
+    EventLoopScheduler { inner }
+  }
+
+  /// Returns true if called from the worker thread of this scheduler.
+  #[inline]
+  pub fn is_current(&self) -> bool {
+    std::thread::current().id() == self.inner.thread_id
+  }
+
+  /// Stops the worker thread after the task it's running, the pending tasks
+  /// and the tasks scheduled later are dropped without running.
+  pub fn shutdown(&self) {
+    self.inner.state.lock().unwrap().shutdown = true;
+    self.inner.cvar.notify_all();
+  }
+
+  /// Returns true if `shutdown` was called.
+  pub fn is_shutdown(&self) -> bool {
+    self.inner.state.lock().unwrap().shutdown
+  }
+
+  /// Blocks the current thread until the worker thread exits, which happens
+  /// only after `shutdown`. Returns immediately if the thread was already
+  /// joined, or if called from the worker thread itself.
+  ///
+  /// # Panics
+  ///
+  /// Resumes the panic of the first task which panicked, once.
+  pub fn join(&self) {
+    if self.is_current() {
+      return;
+    }
+    let thread = self.inner.thread.lock().unwrap().take();
+    if let Some(Err(panic)) = thread.map(JoinHandle::join) {
+      std::panic::resume_unwind(panic);
+    }
+    let panic = self.inner.panic.lock().unwrap().take();
+    if let Some(panic) = panic {
+      std::panic::resume_unwind(panic);
+    }
+  }
+
+  fn add_task(
+    &self,
+    task: Box<dyn FnMut(usize) + Send>,
+    due: Instant,
+    period: Option<(Duration, IntervalPolicy)>,
+  ) -> SpawnHandle {
+    let mut cancel = SpawnHandle::new(AbortHandle::new_pair().0);
+    let mut state = self.inner.state.lock().unwrap();
+    if state.shutdown {
+      cancel.unsubscribe();
+      return cancel;
+    }
+    let seq = state.seq;
+    state.seq += 1;
+    state.queue.push(Reverse(Entry {
+      due,
+      seq,
+      period,
+      invokes: 0,
+      cancel: cancel.clone(),
+      task,
+    }));
+    self.inner.cvar.notify_all();
+    cancel
+  }
+}
+
+impl Inner {
+  fn run(&self) {
+    let mut state = self.state.lock().unwrap();
+    while !state.shutdown {
+      let now = Instant::now();
+      match state.queue.peek() {
+        None => state = self.cvar.wait(state).unwrap(),
+        Some(Reverse(e)) if e.due > now => {
+          let timeout = e.due - now;
+          state = self.cvar.wait_timeout(state, timeout).unwrap().0;
+        }
+        Some(_) => {
+          let Reverse(mut entry) = state.queue.pop().unwrap();
+          drop(state);
+          if !entry.cancel.is_closed() {
+            let task = &mut entry.task;
+            let invokes = entry.invokes;
+            let result =
+              std::panic::catch_unwind(AssertUnwindSafe(|| task(invokes)));
+            if let Err(panic) = result {
+              entry.cancel.unsubscribe();
+              self.panic.lock().unwrap().get_or_insert(panic);
+            }
+          }
+          state = self.state.lock().unwrap();
+          if let Some((period, policy)) = entry.period {
+            if !entry.cancel.is_closed() {
+              entry.due =
+                policy.next_deadline(entry.due, period, Instant::now());
+              entry.invokes += 1;
+              state.queue.push(Reverse(entry));
+            }
+          }
+        }
+      }
+    }
+    // Drop the pending tasks out of the lock, they may schedule again.
+    let pending = std::mem::take(&mut state.queue);
+    drop(state);
+    drop(pending);
+  }
+}
+
+/// A future spawned on an `EventLoopScheduler`, polled by a task scheduled
+/// every time it's woken.
+struct FutureTask {
+  future: Mutex<Option<BoxFuture<'static, ()>>>,
+  scheduler: EventLoopScheduler,
+}
+
+impl FutureTask {
+  fn poll(self: &Arc<Self>) {
+    let mut future = self.future.lock().unwrap();
+    if let Some(fut) = future.as_mut() {
+      let waker = waker_ref(self);
+      let cx = &mut Context::from_waker(&waker);
+      if fut.as_mut().poll(cx).is_ready() {
+        *future = None;
+      }
+    }
+  }
+}
+
+impl ArcWake for FutureTask {
+  fn wake_by_ref(arc_self: &Arc<Self>) {
+    let task = arc_self.clone();
+    arc_self.scheduler.add_task(
+      Box::new(move |_| task.poll()),
+      Instant::now(),
+      None,
+    );
+  }
+}
+
+impl SharedScheduler for EventLoopScheduler {
+  fn spawn<Fut>(&self, future: Fut)
+  where
+    Fut: Future<Output = ()> + Send + 'static,
+  {
+    let task = Arc::new(FutureTask {
+      future: Mutex::new(Some(Box::pin(future))),
+      scheduler: self.clone(),
+    });
+    ArcWake::wake(task);
+  }
+
+  fn schedule<T: Send + 'static>(
+    &self,
+    task: impl FnOnce(T) + Send + 'static,
+    delay: Option<Duration>,
+    state: T,
+  ) -> SpawnHandle {
+    let mut task = Some((task, state));
+    let task = move |_| {
+      if let Some((task, state)) = task.take() {
+        task(state)
+      }
+    };
+    let due = Instant::now() + delay.unwrap_or_default();
+    self.add_task(Box::new(task), due, None)
+  }
+
+  fn schedule_repeating(
+    &self,
+    task: impl FnMut(usize) + Send + 'static,
+    time_between: Duration,
+    at: Option<Instant>,
+    policy: IntervalPolicy,
+  ) -> SpawnHandle {
+    let due = at.unwrap_or_else(|| Instant::now() + time_between);
+    self.add_task(Box::new(task), due, Some((time_between, policy)))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use std::sync::mpsc::channel;
+
+  #[test]
+  fn deadline_order_on_one_thread() {
+    let scheduler = EventLoopScheduler::new();
+    let (tx, rx) = channel();
+    for (v, delay) in [(3, 30), (1, 10), (2, 20), (0, 0)] {
+      let tx = tx.clone();
+      let c_scheduler = scheduler.clone();
+      scheduler.schedule(
+        move |v| tx.send((v, c_scheduler.is_current())).unwrap(),
+        Some(Duration::from_millis(delay)),
+        v,
+      );
+    }
+    drop(tx);
+
+    let received: Vec<_> = rx.iter().take(4).collect();
+    assert_eq!(received, vec![(0, true), (1, true), (2, true), (3, true)]);
+    assert!(!scheduler.is_current());
+    scheduler.shutdown();
+    scheduler.join();
+  }
+
+  #[test]
+  fn interval_and_observe_on() {
+    let scheduler = EventLoopScheduler::new();
+    let values = Arc::new(Mutex::new(vec![]));
+    let c_values = values.clone();
+    let c_scheduler = scheduler.clone();
+    observable::interval(Duration::from_millis(1), scheduler.clone())
+      .take(3)
+      .observe_on(scheduler.clone())
+      .into_shared()
+      .subscribe_blocking(move |v| {
+        assert!(c_scheduler.is_current());
+        c_values.lock().unwrap().push(v)
+      });
+
+    assert_eq!(*values.lock().unwrap(), vec![0, 1, 2]);
+    scheduler.shutdown();
+    scheduler.join();
+  }
+
+  #[test]
+  fn cancel() {
+    let scheduler = EventLoopScheduler::new();
+    let (tx, rx) = channel();
+    let c_tx = tx.clone();
+    let mut handle = scheduler.schedule(
+      move |v| c_tx.send(v).unwrap(),
+      Some(Duration::from_millis(10)),
+      1,
+    );
+    scheduler.schedule(
+      move |v| tx.send(v).unwrap(),
+      Some(Duration::from_millis(20)),
+      2,
+    );
+    handle.unsubscribe();
+
+    assert_eq!(rx.recv().unwrap(), 2);
+    scheduler.shutdown();
+    scheduler.join();
+  }
+
+  #[test]
+  fn survive_panicking_task() {
+    let scheduler = EventLoopScheduler::new();
+    let (tx, rx) = channel();
+    let c_tx = tx.clone();
+    let panicking = scheduler.schedule_repeating(
+      move |i| {
+        c_tx.send(i).unwrap();
+        panic!("task failed")
+      },
+      Duration::from_millis(1),
+      None,
+      IntervalPolicy::Burst,
+    );
+    scheduler.schedule(
+      move |v| tx.send(v).unwrap(),
+      Some(Duration::from_millis(10)),
+      10,
+    );
+
+    assert_eq!(rx.iter().collect::<Vec<_>>(), vec![0, 10]);
+    assert!(panicking.is_closed());
+    scheduler.shutdown();
+    let joined = std::panic::catch_unwind(|| scheduler.join());
+    assert!(joined.is_err());
+    scheduler.join();
+  }
+
+  #[test]
+  fn shutdown_drops_pending() {
+    let scheduler = EventLoopScheduler::new();
+    let (tx, rx) = channel::<i32>();
+    let c_tx = tx.clone();
+    scheduler.schedule(
+      move |v| c_tx.send(v).unwrap(),
+      Some(Duration::from_secs(10)),
+      1,
+    );
+    scheduler.shutdown();
+    scheduler.join();
+    assert!(scheduler.is_shutdown());
+
+    let handle = scheduler.schedule(move |v| tx.send(v).unwrap(), None, 2);
+    assert!(handle.is_closed());
+    assert!(rx.recv().is_err());
+  }
+}
@@ -0,0 +1,82 @@
+use crate::prelude::*;
+use crate::scheduler::Instant;
+use futures::future::AbortHandle;
+use std::future::Future;
+use std::time::Duration;
+
+/// A scheduler running every task synchronously on the calling thread, a
+/// delayed task blocks the thread by sleeping until it's due.
+///
+/// Repeating tasks can't run synchronously, since nothing could cancel them
+/// before `schedule_repeating` returns, so they are queued on the
+/// [`TrampolineScheduler`] of the current thread instead, which runs them on
+/// [`TrampolineScheduler::run`].
+///
+/// [`TrampolineScheduler`]: crate::scheduler::TrampolineScheduler
+#[derive(Clone, Copy, Default)]
+pub struct ImmediateScheduler;
+
+impl LocalScheduler for ImmediateScheduler {
+  fn spawn<Fut>(&self, future: Fut)
+  where
+    Fut: Future<Output = ()> + 'static,
+  {
+    futures::executor::block_on(future);
+  }
+
+  fn schedule<T: 'static>(
+    &self,
+    task: impl FnOnce(T) + 'static,
+    delay: Option<Duration>,
+    state: T,
+  ) -> SpawnHandle {
+    if let Some(delay) = delay {
+      std::thread::sleep(delay);
+    }
+    task(state);
+    let mut handle = SpawnHandle::new(AbortHandle::new_pair().0);
+    handle.unsubscribe();
+    handle
+  }
+
+  fn schedule_repeating(
+    &self,
+    task: impl FnMut(usize) + 'static,
+    time_between: Duration,
+    at: Option<Instant>,
+    policy: IntervalPolicy,
+  ) -> SpawnHandle {
+    TrampolineScheduler.schedule_repeating(task, time_between, at, policy)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use std::time::Instant;
+
+  #[test]
+  fn synchronous() {
+    let values = MutRc::own(vec![]);
+    let c_values = values.clone();
+    observable::from_iter(0..3)
+      .observe_on(ImmediateScheduler)
+      .subscribe(move |v| c_values.rc_deref_mut().push(v));
+    assert_eq!(&*values.rc_deref(), &[0, 1, 2]);
+  }
+
+  #[test]
+  fn delay_by_sleep() {
+    let stamp = Instant::now();
+    let value = MutRc::own(0);
+    let c_value = value.clone();
+    let handle = ImmediateScheduler.schedule(
+      move |v| *c_value.rc_deref_mut() = v,
+      Some(Duration::from_millis(10)),
+      1,
+    );
+    assert!(stamp.elapsed() >= Duration::from_millis(10));
+    assert_eq!(*value.rc_deref(), 1);
+    assert!(handle.is_closed());
+  }
+}
@@ -0,0 +1,370 @@
+use crate::prelude::*;
+use crate::scheduler::Instant;
+use futures::future::AbortHandle;
+use std::cell::RefCell;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::future::Future;
+use std::time::Duration;
+
+/// A scheduler queueing tasks on the current thread.
+///
+/// The outermost `schedule` call of a thread runs every queued task before it
+/// returns, and tasks scheduled while it runs are queued instead of called
+/// recursively. So a task scheduling another one never grows the stack, which
+/// is useful to break the recursion of operators re-subscribing or re-emitting
+/// synchronously.
+///
+/// Delayed tasks wait in a timer queue, the outermost call sleeps until they
+/// are due, so `timer` or `delay` work as on any other scheduler. Repeating
+/// tasks are run by the outermost call only while it waits for delayed tasks
+/// anyway: it can't wait for them alone, since the handle cancelling them,
+/// for example by `take`, is only returned once it's done. Drive them with
+/// [`TrampolineScheduler::run`].
+///
+/// ```rust
+/// # use rxrust::prelude::*;
+/// use std::time::Duration;
+///
+/// let ticks = MutRc::own(vec![]);
+/// let c_ticks = ticks.clone();
+/// observable::interval(Duration::from_millis(1), TrampolineScheduler)
+///   .take(3)
+///   .subscribe(move |v| c_ticks.rc_deref_mut().push(v));
+///
+/// TrampolineScheduler::run();
+/// assert_eq!(&*ticks.rc_deref(), &[0, 1, 2]);
+/// ```
+#[derive(Clone, Copy, Default)]
+pub struct TrampolineScheduler;
+
+#[derive(Default)]
+struct Trampoline {
+  running: bool,
+  seq: u64,
+  queue: BinaryHeap<Reverse<Entry>>,
+}
+
+struct Entry {
+  due: Instant,
+  seq: u64,
+  period: Option<(Duration, IntervalPolicy)>,
+  invokes: usize,
+  cancel: SpawnHandle,
+  task: Box<dyn FnMut(usize)>,
+}
+
+/// Keeps the queue of the current thread running until dropped, also when a
+/// task panics. The tasks left by a panicking task are dropped, the state
+/// they depend on may be broken.
+struct Running;
+
+impl Drop for Running {
+  fn drop(&mut self) {
+    let panicking = std::thread::panicking();
+    let stale = TRAMPOLINE.try_with(|t| {
+      let mut t = t.borrow_mut();
+      t.running = false;
+      if panicking {
+        std::mem::take(&mut t.queue)
+      } else {
+        BinaryHeap::new()
+      }
+    });
+    // Dropped after the borrow is released, a task may touch the queue when
+    // dropped.
+    drop(stale);
+  }
+}
+
+impl PartialEq for Entry {
+  fn eq(&self, other: &Self) -> bool { self.cmp(other) == Ordering::Equal }
+}
+
+impl Eq for Entry {}
+
+impl PartialOrd for Entry {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Ord for Entry {
+  fn cmp(&self, other: &Self) -> Ordering {
+    (self.due, self.seq).cmp(&(other.due, other.seq))
+  }
+}
+
+thread_local! {
+  static TRAMPOLINE: RefCell<Trampoline> = RefCell::new(Trampoline::default());
+}
+
+impl TrampolineScheduler {
+  /// Runs the queue of the current thread until it's empty, sleeping until
+  /// the next task is due, until a repeating task is cancelled. Does nothing
+  /// if called by a task of the queue.
+  pub fn run() {
+    if let Some(_running) = Self::enter() {
+      Self::drive(true);
+    }
+  }
+
+  /// Runs the queue of the current thread, sleeping until the next task is
+  /// due, until it's empty or, unless `repeating`, until only repeating tasks
+  /// are left.
+  fn drive(repeating: bool) {
+    loop {
+      let next = TRAMPOLINE.with(|t| {
+        let t = t.borrow();
+        let waiting = repeating
+          || t
+            .queue
+            .iter()
+            .any(|Reverse(e)| e.period.is_none() && !e.cancel.is_closed());
+        if waiting {
+          t.queue.peek().map(|Reverse(e)| e.due)
+        } else {
+          None
+        }
+      });
+      match next {
+        Some(due) => {
+          let now = Instant::now();
+          if due > now {
+            std::thread::sleep(due - now);
+          }
+          Self::drain_due();
+        }
+        None => break,
+      }
+    }
+  }
+
+  /// Marks the queue of the current thread as running, returns `None` if it
+  /// already is.
+  fn enter() -> Option<Running> {
+    let running =
+      TRAMPOLINE.with(|t| std::mem::replace(&mut t.borrow_mut().running, true));
+    if running { None } else { Some(Running) }
+  }
+
+  fn drain_due() {
+    loop {
+      let entry = TRAMPOLINE.with(|t| {
+        let mut t = t.borrow_mut();
+        let due = t
+          .queue
+          .peek()
+          .is_some_and(|Reverse(e)| e.due <= Instant::now());
+        if due {
+          t.queue.pop().map(|Reverse(e)| e)
+        } else {
+          None
+        }
+      });
+      let Some(mut entry) = entry else { break };
+      if entry.cancel.is_closed() {
+        continue;
+      }
+      (entry.task)(entry.invokes);
+      if let Some((period, policy)) = entry.period {
+        if !entry.cancel.is_closed() {
+          entry.due = policy.next_deadline(entry.due, period, Instant::now());
+          entry.invokes += 1;
+          TRAMPOLINE.with(|t| t.borrow_mut().queue.push(Reverse(entry)));
+        }
+      }
+    }
+  }
+
+  fn add_task(
+    task: Box<dyn FnMut(usize)>,
+    due: Instant,
+    period: Option<(Duration, IntervalPolicy)>,
+  ) -> SpawnHandle {
+    let cancel = SpawnHandle::new(AbortHandle::new_pair().0);
+    let entry = Entry {
+      due,
+      seq: 0,
+      period,
+      invokes: 0,
+      cancel: cancel.clone(),
+      task,
+    };
+    TRAMPOLINE.with(|t| {
+      let mut t = t.borrow_mut();
+      let entry = Entry {
+        seq: t.seq,
+        ..entry
+      };
+      t.seq += 1;
+      t.queue.push(Reverse(entry));
+    });
+    if let Some(_running) = Self::enter() {
+      Self::drive(false);
+    }
+    cancel
+  }
+}
+
+impl LocalScheduler for TrampolineScheduler {
+  fn spawn<Fut>(&self, future: Fut)
+  where
+    Fut: Future<Output = ()> + 'static,
+  {
+    self.schedule(futures::executor::block_on, None, future);
+  }
+
+  fn schedule<T: 'static>(
+    &self,
+    task: impl FnOnce(T) + 'static,
+    delay: Option<Duration>,
+    state: T,
+  ) -> SpawnHandle {
+    let mut task = Some((task, state));
+    let task = move |_| {
+      if let Some((task, state)) = task.take() {
+        task(state)
+      }
+    };
+    let due = Instant::now() + delay.unwrap_or_default();
+    Self::add_task(Box::new(task), due, None)
+  }
+
+  fn schedule_repeating(
+    &self,
+    task: impl FnMut(usize) + 'static,
+    time_between: Duration,
+    at: Option<Instant>,
+    policy: IntervalPolicy,
+  ) -> SpawnHandle {
+    let due = at.unwrap_or_else(|| Instant::now() + time_between);
+    Self::add_task(Box::new(task), due, Some((time_between, policy)))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn queue_instead_of_recursion() {
+    fn recurse(depth: usize, log: MutRc<Vec<usize>>) {
+      log.rc_deref_mut().push(depth);
+      if depth < 100_000 {
+        TrampolineScheduler.schedule(
+          move |log| recurse(depth + 1, log),
+          None,
+          log,
+        );
+      }
+    }
+
+    let log = MutRc::own(vec![]);
+    recurse(0, log.clone());
+    assert_eq!(log.rc_deref().len(), 100_001);
+  }
+
+  #[test]
+  fn recover_from_panicking_task() {
+    let result = std::panic::catch_unwind(|| {
+      TrampolineScheduler.schedule(|_| panic!("task failed"), None, ());
+    });
+    assert!(result.is_err());
+
+    let ran = MutRc::own(false);
+    let c_ran = ran.clone();
+    TrampolineScheduler.schedule(
+      move |_| *c_ran.rc_deref_mut() = true,
+      None,
+      (),
+    );
+    assert!(*ran.rc_deref());
+  }
+
+  #[test]
+  fn run_in_order_after_outermost() {
+    let log = MutRc::own(vec![]);
+    let c_log = log.clone();
+    TrampolineScheduler.schedule(
+      move |_| {
+        let (l1, l2) = (c_log.clone(), c_log.clone());
+        TrampolineScheduler.schedule(
+          move |_| l1.rc_deref_mut().push(2),
+          None,
+          (),
+        );
+        TrampolineScheduler.schedule(
+          move |_| l2.rc_deref_mut().push(3),
+          None,
+          (),
+        );
+        c_log.rc_deref_mut().push(1);
+      },
+      None,
+      (),
+    );
+    assert_eq!(&*log.rc_deref(), &[1, 2, 3]);
+  }
+
+  #[test]
+  fn delay_by_timer_queue() {
+    let log = MutRc::own(vec![]);
+    let c_log = log.clone();
+    TrampolineScheduler.schedule(
+      move |_| {
+        let (l1, l2, l3) = (c_log.clone(), c_log.clone(), c_log.clone());
+        TrampolineScheduler.schedule(
+          move |v| l1.rc_deref_mut().push(v),
+          Some(Duration::from_millis(20)),
+          2,
+        );
+        TrampolineScheduler.schedule(
+          move |v| l2.rc_deref_mut().push(v),
+          Some(Duration::from_millis(10)),
+          1,
+        );
+        TrampolineScheduler
+          .schedule(
+            move |v| l3.rc_deref_mut().push(v),
+            Some(Duration::from_millis(1)),
+            3,
+          )
+          .unsubscribe();
+        assert!(c_log.rc_deref().is_empty());
+      },
+      None,
+      (),
+    );
+    assert_eq!(&*log.rc_deref(), &[1, 2]);
+  }
+
+  #[test]
+  fn drive_timers() {
+    let ticks = MutRc::own(vec![]);
+    let c_ticks = ticks.clone();
+    observable::timer(1, Duration::from_millis(5), TrampolineScheduler)
+      .subscribe(move |v| c_ticks.rc_deref_mut().push(v));
+    assert_eq!(&*ticks.rc_deref(), &[1]);
+
+    let c_ticks = ticks.clone();
+    observable::interval(Duration::from_millis(1), ImmediateScheduler)
+      .take(3)
+      .subscribe(move |v| c_ticks.rc_deref_mut().push(v));
+    assert_eq!(&*ticks.rc_deref(), &[1]);
+
+    // The interval ticks while a delayed task is waited for.
+    TrampolineScheduler.schedule(|_| {}, Some(Duration::from_millis(20)), ());
+    assert_eq!(&*ticks.rc_deref(), &[1, 0, 1, 2]);
+  }
+
+  #[test]
+  fn observe_on() {
+    let values = MutRc::own(vec![]);
+    let c_values = values.clone();
+    observable::from_iter(0..3)
+      .observe_on(TrampolineScheduler)
+      .subscribe(move |v| c_values.rc_deref_mut().push(v));
+    assert_eq!(&*values.rc_deref(), &[0, 1, 2]);
+  }
+}
@@ -3,15 +3,21 @@ use std::ops::DerefMut;
 
 pub mod behavior_subject;
 pub use behavior_subject::*;
+pub mod async_subject;
+pub use async_subject::*;
+pub mod split;
+pub use split::*;
+pub mod unicast_subject;
+pub use unicast_subject::*;
+pub mod topic_subject;
+pub use topic_subject::*;
+pub mod shared_subject;
+pub use shared_subject::*;
 
-pub enum ObserverTrigger<Item, Err> {
-  Item(Item),
-  Err(Err),
-  Complete,
-}
 pub struct InnerSubject<O: Observer + ?Sized, S: SubscriptionLike> {
   observers: Vec<SubjectObserver<Box<O>, S>>,
   subscription: SingleSubscription,
+  stopped: Option<Result<(), O::Err>>,
 }
 
 #[derive(Default, Clone)]
@@ -21,16 +27,6 @@ pub struct Subject<T, B> {
   buffer: B,
 }
 
-pub type SharedSubject<Item, Err> = Subject<
-  MutArc<
-    InnerSubject<
-      dyn Observer<Item = Item, Err = Err> + Send + Sync,
-      MutArc<SingleSubscription>,
-    >,
-  >,
-  MutArc<Vec<ObserverTrigger<Item, Err>>>,
->;
-
 pub type LocalSubject<'a, Item, Err> = Subject<
   MutRc<
     InnerSubject<
@@ -38,118 +34,207 @@ pub type LocalSubject<'a, Item, Err> = Subject<
       MutRc<SingleSubscription>,
     >,
   >,
-  MutRc<Vec<ObserverTrigger<Item, Err>>>,
+  MutRc<Vec<Notification<Item, Err>>>,
 >;
 
-impl<Item, Err> SharedSubject<Item, Err> {
-  #[inline]
-  pub fn new() -> Self { Self::default() }
-}
-
 impl<'a, Item, Err> LocalSubject<'a, Item, Err> {
   #[inline]
   pub fn new() -> Self { Self::default() }
 }
 
-fn emit_buffer<'a, O, B, Item, Err, T>(mut observer: O, b: &'a B)
-where
-  O: DerefMut,
-  O::Target: Observer<Item = Item, Err = Err>,
+fn emit_buffer<'a, I, O, U, B, T>(
+  mut inner: I,
+  b: &'a B,
+  mut reload: impl FnMut() -> Vec<SubjectObserver<Box<O>, U>>,
+) where
+  I: DerefMut<Target = InnerSubject<O, U>>,
+  O: Observer + ?Sized,
+  O::Item: Clone,
+  O::Err: Clone,
+  U: SubscriptionLike + Default + Clone,
   B: RcDerefMut<Target<'a> = T> + 'a,
-  T: DerefMut<Target = Vec<ObserverTrigger<Item, Err>>>,
+  T: DerefMut<Target = Vec<Notification<O::Item, O::Err>>>,
 {
   loop {
     let v = b.rc_deref_mut().pop();
     if let Some(to_emit) = v {
-      match to_emit {
-        ObserverTrigger::Item(v) => observer.next(v),
-        ObserverTrigger::Err(err) => observer.error(err),
-        ObserverTrigger::Complete => observer.complete(),
+      // The observers subscribed while the subject was busy wait in the
+      // chamber, a buffered error or complete has to reach them too.
+      if !matches!(to_emit, Notification::Next(_)) {
+        inner.load(reload());
       }
+      to_emit.accept(&mut *inner);
     } else {
       break;
     }
   }
 }
 
-macro_rules! impl_observer {
-  () => {
-    type Item = Item;
-    type Err = Err;
-
-    fn next(&mut self, value: Self::Item) {
-      if let Ok(mut inner) = self.inner.try_rc_deref_mut() {
-        inner.load(self.chamber.rc_deref_mut().unload());
-        inner.next(value);
-        emit_buffer(inner, &self.buffer)
-      } else {
-        self
-          .buffer
-          .rc_deref_mut()
-          .push(ObserverTrigger::Item(value));
-      }
-    }
+impl<'a, Item: Clone, Err: Clone> Observer for LocalSubject<'a, Item, Err> {
+  type Item = Item;
+  type Err = Err;
 
-    fn error(&mut self, err: Self::Err) {
-      if let Ok(mut inner) = self.inner.try_rc_deref_mut() {
-        inner.load(self.chamber.rc_deref_mut().unload());
-        inner.error(err);
-        emit_buffer(inner, &self.buffer)
-      } else {
-        self.buffer.rc_deref_mut().push(ObserverTrigger::Err(err));
-      }
+  fn next(&mut self, value: Self::Item) {
+    if let Ok(mut inner) = self.inner.try_rc_deref_mut() {
+      inner.load(self.chamber.rc_deref_mut().unload());
+      inner.next(value);
+      emit_buffer(inner, &self.buffer, || self.chamber.rc_deref_mut().unload())
+    } else {
+      self.buffer.rc_deref_mut().push(Notification::Next(value));
     }
+  }
 
-    fn complete(&mut self) {
-      if let Ok(mut inner) = self.inner.try_rc_deref_mut() {
-        inner.load(self.chamber.rc_deref_mut().unload());
-        inner.complete();
-        emit_buffer(inner, &self.buffer)
-      } else {
-        self.buffer.rc_deref_mut().push(ObserverTrigger::Complete);
-      }
+  fn error(&mut self, err: Self::Err) {
+    if !self.chamber.rc_deref_mut().stop(Err(err.clone())) {
+      return;
     }
-  };
-}
-
-impl<'a, Item: Clone, Err: Clone> Observer for LocalSubject<'a, Item, Err> {
-  impl_observer!();
-}
+    if let Ok(mut inner) = self.inner.try_rc_deref_mut() {
+      inner.load(self.chamber.rc_deref_mut().unload());
+      inner.error(err);
+      emit_buffer(inner, &self.buffer, || self.chamber.rc_deref_mut().unload())
+    } else {
+      self.buffer.rc_deref_mut().push(Notification::Error(err));
+    }
+  }
 
-impl<Item: Clone, Err: Clone> Observer for SharedSubject<Item, Err> {
-  impl_observer!();
+  fn complete(&mut self) {
+    if !self.chamber.rc_deref_mut().stop(Ok(())) {
+      return;
+    }
+    if let Ok(mut inner) = self.inner.try_rc_deref_mut() {
+      inner.load(self.chamber.rc_deref_mut().unload());
+      inner.complete();
+      emit_buffer(inner, &self.buffer, || self.chamber.rc_deref_mut().unload())
+    } else {
+      self.buffer.rc_deref_mut().push(Notification::Complete);
+    }
+  }
 }
 
-impl<Item, Err> Observable for SharedSubject<Item, Err> {
+impl<'a, Item, Err> Observable for LocalSubject<'a, Item, Err> {
   type Item = Item;
   type Err = Err;
 }
 
-impl<Item, Err> SharedObservable for SharedSubject<Item, Err> {
-  type Unsub = MutArc<SingleSubscription>;
-  fn actual_subscribe<O>(self, observer: O) -> Self::Unsub
+impl<'a, Item, Err: Clone> LocalObservable<'a> for LocalSubject<'a, Item, Err> {
+  type Unsub = MutRc<SingleSubscription>;
+  fn actual_subscribe<O>(self, mut observer: O) -> Self::Unsub
   where
-    O: Observer<Item = Self::Item, Err = Self::Err> + Sync + Send + 'static,
+    O: Observer<Item = Self::Item, Err = Self::Err> + 'a,
   {
-    self.chamber.rc_deref_mut().subscribe(Box::new(observer))
+    let mut chamber = self.chamber.rc_deref_mut();
+    match chamber.stopped.clone() {
+      Some(stopped) => {
+        drop(chamber);
+        replay_stop(&mut observer, stopped);
+        let mut subscription = MutRc::own(SingleSubscription::default());
+        subscription.unsubscribe();
+        subscription
+      }
+      None => chamber.subscribe(Box::new(observer)),
+    }
   }
 }
 
-impl<'a, Item, Err> Observable for LocalSubject<'a, Item, Err> {
-  type Item = Item;
-  type Err = Err;
+/// Notifies an observer subscribing a stopped subject of how it stopped.
+fn replay_stop<O: Observer>(observer: &mut O, stopped: Result<(), O::Err>) {
+  match stopped {
+    Ok(()) => observer.complete(),
+    Err(err) => observer.error(err),
+  }
 }
 
-impl<'a, Item, Err> LocalObservable<'a> for LocalSubject<'a, Item, Err> {
-  type Unsub = MutRc<SingleSubscription>;
-  fn actual_subscribe<O>(self, observer: O) -> Self::Unsub
-  where
-    O: Observer<Item = Self::Item, Err = Self::Err> + 'a,
-  {
-    self.chamber.rc_deref_mut().subscribe(Box::new(observer))
+impl<'a, Item, Err> LocalSubject<'a, Item, Err> {
+  /// Whether the subject completed or errored. A stopped subject ignores
+  /// further notifications, and observers subscribing to it receive the
+  /// completion or a clone of the error at once.
+  #[inline]
+  pub fn is_stopped(&self) -> bool { self.chamber.rc_deref().stopped.is_some() }
+
+  /// Whether the subject stopped with an error.
+  #[inline]
+  pub fn has_error(&self) -> bool {
+    matches!(self.chamber.rc_deref().stopped, Some(Err(_)))
+  }
+
+  /// Whether the subject has observers which didn't unsubscribe yet. It
+  /// is true while the subject is emitting to its observers.
+  pub fn observed(&self) -> bool {
+    self
+      .inner
+      .try_rc_deref()
+      .map_or(true, |inner| inner.observed())
+      || self.chamber.rc_deref().observed()
   }
 }
 
+/// A subject a [`ConnectableObservable`] can multicast the source `Src`
+/// through.
+///
+/// A local subject subscribes to a `LocalObservable` source and the operators
+/// multicasting through it share their state in a `MutRc`, a shared subject
+/// subscribes to a `SharedObservable` source and shares it in a `MutArc`.
+pub trait SubjectLike<Src>: Observer + Clone {
+  /// The subscription of the subject to the source.
+  type Connection: SubscriptionLike;
+  /// `MutRc` for a local subject, `MutArc` for a shared one.
+  type Rc<T>: Clone;
+
+  /// Subscribes the subject to `source`.
+  fn subscribe_to(self, source: Src) -> Self::Connection;
+
+  /// Wraps `value` in the pointer of the subject's context.
+  fn own<T>(value: T) -> Self::Rc<T>;
+}
+
+macro_rules! impl_subject_like {
+  (local $ty: ident) => {
+    impl<'a, Src, Item, Err> SubjectLike<Src> for $ty<'a, Item, Err>
+    where
+      Src: LocalObservable<'a, Item = Item, Err = Err>,
+      Item: Clone + 'a,
+      Err: Clone + 'a,
+    {
+      type Connection = Src::Unsub;
+      type Rc<T> = MutRc<T>;
+
+      #[inline]
+      fn subscribe_to(self, source: Src) -> Self::Connection {
+        source.actual_subscribe(self)
+      }
+
+      #[inline]
+      fn own<T>(value: T) -> MutRc<T> { MutRc::own(value) }
+    }
+  };
+  (shared $ty: ident) => {
+    impl<Src, Item, Err> SubjectLike<Src> for $ty<Item, Err>
+    where
+      Src: SharedObservable<Item = Item, Err = Err>,
+      Item: Clone + Send + Sync + 'static,
+      Err: Clone + Send + Sync + 'static,
+    {
+      type Connection = Src::Unsub;
+      type Rc<T> = MutArc<T>;
+
+      #[inline]
+      fn subscribe_to(self, source: Src) -> Self::Connection {
+        source.actual_subscribe(self)
+      }
+
+      #[inline]
+      fn own<T>(value: T) -> MutArc<T> { MutArc::own(value) }
+    }
+  };
+}
+
+impl_subject_like!(local LocalSubject);
+impl_subject_like!(shared SharedSubject);
+impl_subject_like!(local LocalBehaviorSubject);
+impl_subject_like!(shared SharedBehaviorSubject);
+impl_subject_like!(local LocalAsyncSubject);
+impl_subject_like!(shared SharedAsyncSubject);
+
 impl<O, U> SubscriptionLike for InnerSubject<O, U>
 where
   O: Observer + ?Sized,
@@ -202,6 +287,7 @@ impl<O: Observer + ?Sized, S: SubscriptionLike> Default for InnerSubject<O, S> {
     InnerSubject {
       observers: vec![],
       subscription: Default::default(),
+      stopped: None,
     }
   }
 }
@@ -278,11 +364,26 @@ where
   fn unload(&mut self) -> Vec<SubjectObserver<Box<O>, U>> {
     std::mem::take(&mut self.observers)
   }
+
+  /// Records how the subject stopped, returns false if it already stopped.
+  fn stop(&mut self, stopped: Result<(), O::Err>) -> bool {
+    if self.stopped.is_some() {
+      false
+    } else {
+      self.stopped = Some(stopped);
+      true
+    }
+  }
+
+  fn observed(&self) -> bool {
+    self.observers.iter().any(|o| !o.subscription.is_closed())
+  }
 }
 
 #[cfg(test)]
 mod test {
   use super::*;
+  use crate::test_observer::TestObserver;
   #[cfg(not(target_arch = "wasm32"))]
   use futures::executor::ThreadPool;
   #[cfg(not(target_arch = "wasm32"))]
@@ -376,6 +477,146 @@ mod test {
     local.error(2);
   }
 
+  #[test]
+  fn replay_complete_to_late_observer() {
+    let mut subject = LocalSubject::<i32, ()>::new();
+    assert!(!subject.is_stopped());
+    subject.complete();
+    subject.error(());
+    assert!(subject.is_stopped());
+    assert!(!subject.has_error());
+
+    let observer = TestObserver::new();
+    let subscription = subject.clone().actual_subscribe(observer.clone());
+    subject.next(1);
+
+    observer
+      .assert_values(&[])
+      .assert_no_errors()
+      .assert_complete();
+    assert!(subscription.is_closed());
+  }
+
+  #[test]
+  fn replay_error_to_late_observer() {
+    let mut subject = LocalSubject::<i32, _>::new();
+    subject.error("oops");
+    subject.complete();
+    assert!(subject.is_stopped());
+    assert!(subject.has_error());
+
+    let observer = TestObserver::new();
+    subject.clone().actual_subscribe(observer.clone());
+    observer
+      .assert_not_complete()
+      .assert_error_matches(|e| *e == "oops");
+  }
+
+  #[test]
+  fn observed() {
+    let mut subject = LocalSubject::<i32, ()>::new();
+    assert!(!subject.observed());
+    let mut s1 = subject.clone().subscribe(|_| {});
+    let mut s2 = subject.clone().subscribe(|_| {});
+    assert!(subject.observed());
+    subject.next(1);
+    s1.unsubscribe();
+    assert!(subject.observed());
+    s2.unsubscribe();
+    assert!(!subject.observed());
+
+    subject.clone().subscribe(|_| {});
+    subject.complete();
+    assert!(!subject.observed());
+  }
+
+  #[cfg(not(target_arch = "wasm32"))]
+  #[test]
+  fn shared_replay_stop() {
+    let mut subject = SharedSubject::<i32, ()>::new();
+    let observer = TestObserver::new();
+    subject.clone().into_shared().subscribe(|_| {});
+    assert!(subject.observed());
+    subject.complete();
+    assert!(!subject.observed());
+    assert!(subject.is_stopped());
+
+    subject
+      .clone()
+      .into_shared()
+      .actual_subscribe(observer.clone());
+    observer.assert_complete();
+  }
+
+  #[cfg(not(target_arch = "wasm32"))]
+  #[test]
+  fn shared_subscribe_while_completing() {
+    for _ in 0..100 {
+      let subject = SharedSubject::<i32, ()>::new();
+      let handles = (0..4)
+        .map(|_| {
+          let subject = subject.clone();
+          std::thread::spawn(move || {
+            (0..10)
+              .map(|_| {
+                let observer = TestObserver::new();
+                subject
+                  .clone()
+                  .into_shared()
+                  .actual_subscribe(observer.clone());
+                observer
+              })
+              .collect::<Vec<_>>()
+          })
+        })
+        .collect::<Vec<_>>();
+      subject.clone().complete();
+
+      handles
+        .into_iter()
+        .flat_map(|h| h.join().unwrap())
+        .for_each(|observer| {
+          observer.assert_complete();
+        });
+    }
+  }
+
+  #[test]
+  fn complete_observer_subscribed_while_emitting() {
+    let mut subject = LocalSubject::<i32, ()>::new();
+    let late = TestObserver::new();
+    let c_late = late.clone();
+    let mut c_subject = subject.clone();
+    subject.clone().subscribe(move |_| {
+      c_subject.clone().actual_subscribe(c_late.clone());
+      c_subject.complete();
+    });
+
+    subject.next(1);
+    late.assert_values(&[]).assert_complete();
+    assert_eq!(subject.teardown_size(), 0);
+  }
+
+  #[cfg(not(target_arch = "wasm32"))]
+  #[test]
+  fn shared_complete_observer_subscribed_while_emitting() {
+    let mut subject = SharedSubject::<i32, ()>::new();
+    let late = TestObserver::new();
+    let c_late = late.clone();
+    let mut c_subject = subject.clone();
+    subject.clone().into_shared().subscribe(move |_| {
+      c_subject
+        .clone()
+        .into_shared()
+        .actual_subscribe(c_late.clone());
+      c_subject.complete();
+    });
+
+    subject.next(1);
+    late.assert_values(&[]).assert_complete();
+    assert_eq!(subject.teardown_size(), 0);
+  }
+
   #[test]
   fn fix_recursive_next() {
     let mut subject = Subject::default();
@@ -389,7 +630,7 @@ mod test {
 
     subject.next(0);
 
-    let mut subject = Subject::default();
+    let mut subject = SharedSubject::default();
     let mut c_subject = subject.clone();
 
     subject.clone().into_shared().subscribe(move |i| {
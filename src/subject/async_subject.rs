@@ -0,0 +1,245 @@
+use crate::prelude::*;
+
+/// A subject emitting only the last value it received, and only when it
+/// completes.
+///
+/// Observers subscribing after the completion receive that value and the
+/// completion at once, observers subscribing after an error receive the error.
+#[derive(Clone, Default)]
+pub struct AsyncSubject<S, V> {
+  pub(crate) subject: S,
+  pub(crate) state: V,
+}
+
+/// The last value an [`AsyncSubject`] received, and how it stopped.
+pub struct AsyncState<Item, Err> {
+  last: Option<Item>,
+  stopped: Option<Result<(), Err>>,
+}
+
+pub type LocalAsyncSubject<'a, Item, Err> =
+  AsyncSubject<LocalSubject<'a, Item, Err>, MutRc<AsyncState<Item, Err>>>;
+
+pub type SharedAsyncSubject<Item, Err> =
+  AsyncSubject<SharedSubject<Item, Err>, MutArc<AsyncState<Item, Err>>>;
+
+impl<Item, Err> Default for AsyncState<Item, Err> {
+  #[inline]
+  fn default() -> Self {
+    AsyncState {
+      last: None,
+      stopped: None,
+    }
+  }
+}
+
+impl<'a, Item, Err> LocalAsyncSubject<'a, Item, Err> {
+  #[inline]
+  pub fn new() -> Self { Self::default() }
+}
+
+impl<Item, Err> SharedAsyncSubject<Item, Err> {
+  #[inline]
+  pub fn new() -> Self { Self::default() }
+}
+
+impl<S: SubscriptionLike, V> SubscriptionLike for AsyncSubject<S, V> {
+  #[inline]
+  fn unsubscribe(&mut self) { self.subject.unsubscribe(); }
+
+  #[inline]
+  fn is_closed(&self) -> bool { self.subject.is_closed() }
+}
+
+macro_rules! impl_observer {
+  ($rc: ident) => {
+    impl<S, Item, Err> Observer for AsyncSubject<S, $rc<AsyncState<Item, Err>>>
+    where
+      S: Observer<Item = Item, Err = Err>,
+      Item: Clone,
+      Err: Clone,
+    {
+      type Item = Item;
+      type Err = Err;
+
+      fn next(&mut self, value: Item) {
+        let mut state = self.state.rc_deref_mut();
+        if state.stopped.is_none() {
+          state.last = Some(value);
+        }
+      }
+
+      fn error(&mut self, err: Err) {
+        {
+          let mut state = self.state.rc_deref_mut();
+          if state.stopped.is_some() {
+            return;
+          }
+          state.stopped = Some(Err(err.clone()));
+        }
+        self.subject.error(err)
+      }
+
+      fn complete(&mut self) {
+        let last = {
+          let mut state = self.state.rc_deref_mut();
+          if state.stopped.is_some() {
+            return;
+          }
+          state.stopped = Some(Ok(()));
+          state.last.clone()
+        };
+        if let Some(last) = last {
+          self.subject.next(last);
+        }
+        self.subject.complete()
+      }
+    }
+  };
+}
+
+impl_observer!(MutRc);
+impl_observer!(MutArc);
+
+impl<S: TearDownSize, V> TearDownSize for AsyncSubject<S, V> {
+  #[inline]
+  fn teardown_size(&self) -> usize { self.subject.teardown_size() }
+}
+
+impl<Item, Err> AsyncState<Item, Err>
+where
+  Item: Clone,
+  Err: Clone,
+{
+  /// Replays the termination to `observer`, returns false if not terminated.
+  fn replay<O>(&self, observer: &mut O) -> bool
+  where
+    O: Observer<Item = Item, Err = Err>,
+  {
+    match &self.stopped {
+      None => return false,
+      Some(Ok(())) => {
+        if let Some(last) = &self.last {
+          observer.next(last.clone());
+        }
+        observer.complete();
+      }
+      Some(Err(err)) => observer.error(err.clone()),
+    }
+    true
+  }
+}
+
+impl<Item, Err> Observable for SharedAsyncSubject<Item, Err> {
+  type Item = Item;
+  type Err = Err;
+}
+
+impl<Item, Err> SharedObservable for SharedAsyncSubject<Item, Err>
+where
+  Item: Clone,
+  Err: Clone,
+{
+  type Unsub = MutArc<SingleSubscription>;
+  fn actual_subscribe<O>(self, mut observer: O) -> Self::Unsub
+  where
+    O: Observer<Item = Self::Item, Err = Self::Err> + Sync + Send + 'static,
+  {
+    if self.state.rc_deref().replay(&mut observer) {
+      let subscription = MutArc::own(SingleSubscription::default());
+      subscription.clone().unsubscribe();
+      subscription
+    } else {
+      self.subject.actual_subscribe(observer)
+    }
+  }
+}
+
+impl<'a, Item, Err> Observable for LocalAsyncSubject<'a, Item, Err> {
+  type Item = Item;
+  type Err = Err;
+}
+
+impl<'a, Item, Err> LocalObservable<'a> for LocalAsyncSubject<'a, Item, Err>
+where
+  Item: Clone,
+  Err: Clone,
+{
+  type Unsub = MutRc<SingleSubscription>;
+  fn actual_subscribe<O>(self, mut observer: O) -> Self::Unsub
+  where
+    O: Observer<Item = Self::Item, Err = Self::Err> + 'a,
+  {
+    if self.state.rc_deref().replay(&mut observer) {
+      let subscription = MutRc::own(SingleSubscription::default());
+      subscription.clone().unsubscribe();
+      subscription
+    } else {
+      self.subject.actual_subscribe(observer)
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use crate::prelude::*;
+  use crate::test_observer::TestObserver;
+
+  #[test]
+  fn emit_last_on_complete() {
+    let observer = TestObserver::<i32, ()>::new();
+    let mut subject = LocalAsyncSubject::new();
+    subject.clone().actual_subscribe(observer.clone());
+    subject.next(1);
+    subject.next(2);
+    observer.assert_values(&[]).assert_not_complete();
+
+    subject.complete();
+    subject.next(3);
+    observer.assert_values(&[2]).assert_complete();
+  }
+
+  #[test]
+  fn replay_to_late_observer() {
+    let mut subject = LocalAsyncSubject::<_, ()>::new();
+    subject.next(1);
+    subject.complete();
+
+    let observer = TestObserver::new();
+    let subscription = subject.clone().actual_subscribe(observer.clone());
+
+    observer.assert_values(&[1]).assert_complete();
+    assert!(subscription.is_closed());
+  }
+
+  #[test]
+  fn error() {
+    let mut subject = LocalAsyncSubject::<i32, _>::new();
+    subject.next(1);
+    subject.error("oops");
+    subject.complete();
+
+    let observer = TestObserver::new();
+    subject.clone().actual_subscribe(observer.clone());
+    observer
+      .assert_values(&[])
+      .assert_not_complete()
+      .assert_error_matches(|e| *e == "oops");
+  }
+
+  #[cfg(not(target_arch = "wasm32"))]
+  #[test]
+  fn shared() {
+    let mut subject = SharedAsyncSubject::<_, ()>::new();
+    let observer = TestObserver::new();
+    subject
+      .clone()
+      .into_shared()
+      .actual_subscribe(observer.clone());
+    subject.next(1);
+    subject.next(2);
+    subject.complete();
+
+    observer.assert_values(&[2]).assert_complete();
+  }
+}
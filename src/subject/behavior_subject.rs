@@ -20,6 +20,19 @@ impl<'a, Item, Err> LocalBehaviorSubject<'a, Item, Err> {
       value: MutRc::own(value),
     }
   }
+
+  /// Whether the subject completed or errored, observers subscribing to a
+  /// stopped subject don't receive its value.
+  #[inline]
+  pub fn is_stopped(&self) -> bool { self.subject.is_stopped() }
+
+  /// Whether the subject stopped with an error.
+  #[inline]
+  pub fn has_error(&self) -> bool { self.subject.has_error() }
+
+  /// Whether the subject has observers which didn't unsubscribe yet.
+  #[inline]
+  pub fn observed(&self) -> bool { self.subject.observed() }
 }
 impl<Item, Err> SharedBehaviorSubject<Item, Err> {
   #[inline]
@@ -29,6 +42,29 @@ impl<Item, Err> SharedBehaviorSubject<Item, Err> {
       value: MutArc::own(value),
     }
   }
+
+  /// Whether the subject completed or errored, observers subscribing to a
+  /// stopped subject don't receive its value.
+  #[inline]
+  pub fn is_stopped(&self) -> bool { self.subject.is_stopped() }
+
+  /// Whether the subject stopped with an error.
+  #[inline]
+  pub fn has_error(&self) -> bool { self.subject.has_error() }
+
+  /// Whether the subject has observers which didn't unsubscribe yet.
+  #[inline]
+  pub fn observed(&self) -> bool { self.subject.observed() }
+}
+
+impl<'a, Item, Err> From<Item> for LocalBehaviorSubject<'a, Item, Err> {
+  #[inline]
+  fn from(value: Item) -> Self { Self::new(value) }
+}
+
+impl<Item, Err> From<Item> for SharedBehaviorSubject<Item, Err> {
+  #[inline]
+  fn from(value: Item) -> Self { Self::new(value) }
 }
 
 impl<S: SubscriptionLike, V> SubscriptionLike for BehaviorSubject<S, V> {
@@ -39,30 +75,122 @@ impl<S: SubscriptionLike, V> SubscriptionLike for BehaviorSubject<S, V> {
   fn is_closed(&self) -> bool { self.subject.is_closed() }
 }
 
-impl<S, Item> Observer for BehaviorSubject<S, Item>
-where
-  S: Observer,
-  S::Item: Clone,
-  Item: RcDerefMut,
-  for<'r> Item::Target<'r>: std::ops::DerefMut<Target = S::Item>,
-{
-  type Item = S::Item;
-  type Err = S::Err;
+macro_rules! impl_observer {
+  ($rc: ident) => {
+    impl<S, Item> Observer for BehaviorSubject<S, $rc<Item>>
+    where
+      S: Observer<Item = Item>,
+      Item: Clone,
+    {
+      type Item = Item;
+      type Err = S::Err;
 
-  #[inline]
-  fn next(&mut self, value: Self::Item) {
-    let mut v = self.value.rc_deref_mut();
-    *v = value;
-    self.subject.next(v.clone())
-  }
+      #[inline]
+      fn next(&mut self, value: Item) {
+        let mut v = self.value.rc_deref_mut();
+        *v = value;
+        self.subject.next(v.clone())
+      }
 
-  #[inline]
-  fn error(&mut self, err: Self::Err) { self.subject.error(err) }
+      #[inline]
+      fn error(&mut self, err: Self::Err) { self.subject.error(err) }
 
-  #[inline]
-  fn complete(&mut self) { self.subject.complete() }
+      #[inline]
+      fn complete(&mut self) { self.subject.complete() }
+    }
+  };
 }
 
+impl_observer!(MutRc);
+impl_observer!(MutArc);
+
+macro_rules! impl_value_access {
+  ($rc: ident) => {
+    impl<S, Item> BehaviorSubject<S, $rc<Item>>
+    where
+      S: Observer<Item = Item>,
+      Item: Clone,
+    {
+      /// Returns a clone of the current value.
+      ///
+      /// `update` and `next` emit while the value is locked, so their
+      /// observers can't read it, a local subject panics and a shared one
+      /// deadlocks.
+      #[inline]
+      pub fn value(&self) -> Item { self.value.rc_deref().clone() }
+
+      /// Calls `f` with a reference to the current value, without cloning it.
+      ///
+      /// The value is locked while `f` runs, so `f` must not emit through the
+      /// subject, and as with [`value`](Self::value) the observers of
+      /// `update` and `next` can't call it.
+      #[inline]
+      pub fn with_value<R>(&self, f: impl FnOnce(&Item) -> R) -> R {
+        f(&self.value.rc_deref())
+      }
+
+      /// Mutates the current value with `f` and emits the result.
+      ///
+      /// The value stays locked until the observers received it, so concurrent
+      /// updates are emitted in the order they were applied. Don't access the
+      /// subject from its observers during the emission.
+      pub fn update(&mut self, f: impl FnOnce(&mut Item)) {
+        let mut v = self.value.rc_deref_mut();
+        f(&mut v);
+        self.subject.next(v.clone())
+      }
+
+      /// Replaces and emits the current value with `new` if it equals
+      /// `current`, returns whether it was replaced.
+      ///
+      /// Unlike `update`, the value is released before the emission, so the
+      /// observers can read it, but concurrent replacements may be emitted
+      /// in another order than they were applied.
+      pub fn compare_and_set(&mut self, current: &Item, new: Item) -> bool
+      where
+        Item: PartialEq,
+      {
+        let swapped = {
+          let mut v = self.value.rc_deref_mut();
+          (*v == *current).then(|| {
+            *v = new;
+            v.clone()
+          })
+        };
+        // Emit after releasing the value, the observers may read it.
+        let swap = swapped.is_some();
+        if let Some(value) = swapped {
+          self.subject.next(value);
+        }
+        swap
+      }
+
+      /// Emits `value` only if it differs from the current value, returns
+      /// whether it was emitted.
+      pub fn next_if_changed(&mut self, value: Item) -> bool
+      where
+        Item: PartialEq,
+      {
+        let changed = {
+          let mut v = self.value.rc_deref_mut();
+          (*v != value).then(|| {
+            *v = value;
+            v.clone()
+          })
+        };
+        let emit = changed.is_some();
+        if let Some(value) = changed {
+          self.subject.next(value);
+        }
+        emit
+      }
+    }
+  };
+}
+
+impl_value_access!(MutRc);
+impl_value_access!(MutArc);
+
 impl<S: TearDownSize, V> TearDownSize for BehaviorSubject<S, V> {
   #[inline]
   fn teardown_size(&self) -> usize { self.subject.teardown_size() }
@@ -83,7 +211,7 @@ where
   where
     O: Observer<Item = Self::Item, Err = Self::Err> + Sync + Send + 'static,
   {
-    if !self.subject.is_closed() {
+    if !self.subject.is_closed() && !self.subject.is_stopped() {
       observer.next(self.value.rc_deref().clone());
     }
     let o = Box::new(observer);
@@ -96,7 +224,7 @@ impl<'a, Item, Err> Observable for LocalBehaviorSubject<'a, Item, Err> {
   type Err = Err;
 }
 
-impl<'a, Item: Clone, Err> LocalObservable<'a>
+impl<'a, Item: Clone, Err: Clone> LocalObservable<'a>
   for LocalBehaviorSubject<'a, Item, Err>
 {
   type Unsub = MutRc<SingleSubscription>;
@@ -104,7 +232,7 @@ impl<'a, Item: Clone, Err> LocalObservable<'a>
   where
     O: Observer<Item = Self::Item, Err = Self::Err> + 'a,
   {
-    if !self.subject.is_closed() {
+    if !self.subject.is_closed() && !self.subject.is_stopped() {
       observer.next(self.value.rc_deref().clone());
     }
     self.subject.actual_subscribe(observer)
@@ -114,6 +242,7 @@ impl<'a, Item: Clone, Err> LocalObservable<'a>
 #[cfg(test)]
 mod test {
   use crate::prelude::*;
+  use crate::test_observer::TestObserver;
   #[cfg(not(target_arch = "wasm32"))]
   use futures::executor::ThreadPool;
   #[cfg(not(target_arch = "wasm32"))]
@@ -199,6 +328,74 @@ mod test {
     assert_eq!(*c_v.lock().unwrap(), 42);
   }
 
+  #[test]
+  fn value_access() {
+    let mut subject = LocalBehaviorSubject::<_, ()>::new(1);
+    let observer = TestObserver::new();
+    subject.clone().actual_subscribe(observer.clone());
+
+    assert_eq!(subject.value(), 1);
+    subject.update(|v| *v += 1);
+    assert_eq!(subject.with_value(|v| *v * 10), 20);
+
+    assert!(!subject.compare_and_set(&1, 5));
+    assert!(subject.compare_and_set(&2, 3));
+    assert!(!subject.next_if_changed(3));
+    assert!(subject.next_if_changed(4));
+
+    assert_eq!(subject.value(), 4);
+    observer.assert_values(&[1, 2, 3, 4]);
+  }
+
+  #[test]
+  fn read_value_while_emitting() {
+    let mut subject = LocalBehaviorSubject::<_, ()>::new(1);
+    let read = MutRc::own(vec![]);
+    let (c_subject, c_read) = (subject.clone(), read.clone());
+    subject
+      .clone()
+      .subscribe(move |_| c_read.rc_deref_mut().push(c_subject.value()));
+
+    assert!(subject.next_if_changed(2));
+    assert!(subject.compare_and_set(&2, 3));
+    assert_eq!(*read.rc_deref(), vec![1, 2, 3]);
+  }
+
+  #[cfg(not(target_arch = "wasm32"))]
+  #[test]
+  fn shared_concurrent_update() {
+    let subject = SharedBehaviorSubject::<_, ()>::new(0);
+    let observer = TestObserver::new();
+    subject
+      .clone()
+      .into_shared()
+      .actual_subscribe(observer.clone());
+
+    let handles = (0..4)
+      .map(|_| {
+        let mut subject = subject.clone();
+        std::thread::spawn(move || {
+          (0..100).for_each(|_| subject.update(|v| *v += 1))
+        })
+      })
+      .collect::<Vec<_>>();
+    handles.into_iter().for_each(|h| h.join().unwrap());
+
+    assert_eq!(subject.value(), 400);
+    observer.assert_values(&(0..=400).collect::<Vec<_>>());
+  }
+
+  #[test]
+  fn stopped_without_value() {
+    let mut subject = LocalBehaviorSubject::<_, ()>::new(42);
+    subject.complete();
+    assert!(subject.is_stopped());
+
+    let observer = TestObserver::new();
+    subject.clone().actual_subscribe(observer.clone());
+    observer.assert_values(&[]).assert_complete();
+  }
+
   #[test]
   fn subject_subscribe_subject() {
     let mut local = LocalBehaviorSubject::new(42);
@@ -0,0 +1,592 @@
+use crate::prelude::*;
+use arc_swap::ArcSwap;
+use std::{
+  collections::VecDeque,
+  sync::{
+    Arc, Mutex, MutexGuard, PoisonError, TryLockError,
+    atomic::{AtomicBool, Ordering},
+  },
+};
+
+/// A thread-safe subject built for many producers.
+///
+/// Emitting doesn't lock the subject: the observers are kept in a
+/// copy-on-write list which producers read without waiting on each other,
+/// and each value is wrapped in one `Arc` shared by all the observers, which
+/// clone it only when they receive it and the last one takes it. Emit an
+/// `Arc` to share the values without cloning them at all. Only the observer
+/// receiving a value is locked, so producers emitting concurrently don't
+/// serialize, but an observer may receive their values in any order.
+///
+/// A producer finding an observer busy leaves the notification to the call
+/// holding it, so an observer may also emit to the subject again: it receives
+/// that notification once its current one returns, while the other observers
+/// may receive it at once, before the current one. An observer which panics
+/// is dropped and its subscription closed.
+///
+/// A subject created by [`SharedSubject::ordered`] queues the notifications
+/// instead, and the producer which finds the queue idle emits them in order
+/// for all the others. Observers of an ordered subject may emit to it again,
+/// the notifications are queued after the current ones.
+///
+/// ```
+/// use rxrust::prelude::*;
+/// use std::sync::{Arc, Mutex};
+///
+/// let subject = SharedSubject::<i32, ()>::new();
+/// let sum = Arc::new(Mutex::new(0));
+/// let c_sum = sum.clone();
+/// subject
+///   .clone()
+///   .into_shared()
+///   .subscribe(move |v| *c_sum.lock().unwrap() += v);
+///
+/// let handles = (0..4)
+///   .map(|i| {
+///     let mut subject = subject.clone();
+///     std::thread::spawn(move || subject.next(i))
+///   })
+///   .collect::<Vec<_>>();
+/// handles.into_iter().for_each(|h| h.join().unwrap());
+/// assert_eq!(*sum.lock().unwrap(), 6);
+/// ```
+pub struct SharedSubject<Item, Err>(Arc<SharedInner<Item, Err>>);
+
+type BoxObserver<Item, Err> =
+  Box<dyn Observer<Item = Item, Err = Err> + Send + Sync>;
+
+struct SharedObserver<Item, Err> {
+  /// Taken when the observer receives an error or the completion.
+  observer: Mutex<Option<BoxObserver<Item, Err>>>,
+  /// The notifications left to the call holding the observer.
+  pending: Mutex<VecDeque<Notification<Arc<Item>, Err>>>,
+  subscription: MutArc<SingleSubscription>,
+}
+
+struct SharedInner<Item, Err> {
+  observers: ArcSwap<Vec<Arc<SharedObserver<Item, Err>>>>,
+  stopped: Mutex<Option<Result<(), Err>>>,
+  is_stopped: AtomicBool,
+  closed: AtomicBool,
+  /// The pending notifications of an ordered subject, and whether a producer
+  /// is emitting them.
+  queue: Option<Mutex<NotificationQueue<Item, Err>>>,
+}
+
+type NotificationQueue<Item, Err> =
+  (VecDeque<Notification<Arc<Item>, Err>>, bool);
+
+/// Locks `mutex`, the state it guards stays consistent even if a thread
+/// panicked while holding it.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+  mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Marks the queue of an ordered subject idle if the producer emitting it
+/// panics, so the next producer emits the notifications left.
+struct Emitting<'a, Item, Err>(&'a Mutex<NotificationQueue<Item, Err>>);
+
+impl<'a, Item, Err> Drop for Emitting<'a, Item, Err> {
+  fn drop(&mut self) {
+    if std::thread::panicking() {
+      lock(self.0).1 = false;
+    }
+  }
+}
+
+impl<Item: Clone, Err> SharedObserver<Item, Err> {
+  /// Delivers `notification` to the observer, or leaves it to the call
+  /// holding the observer, which may be an outer call of this thread if the
+  /// observer emits to the subject again.
+  fn deliver(&self, notification: Notification<Arc<Item>, Err>) {
+    let mut notification = Some(notification);
+    let mut observer = loop {
+      match self.observer.try_lock() {
+        Ok(observer) => break observer,
+        // Leave the notification, and retry in case the call holding the
+        // observer released it before.
+        Err(TryLockError::WouldBlock) => match notification.take() {
+          Some(notification) => lock(&self.pending).push_back(notification),
+          None => return,
+        },
+        Err(TryLockError::Poisoned(poisoned)) => {
+          // The observer panicked, its state can't be trusted anymore.
+          *poisoned.into_inner() = None;
+          lock(&self.pending).clear();
+          self.subscription.clone().unsubscribe();
+          return;
+        }
+      }
+    };
+    if let Some(notification) = notification {
+      Self::accept(&mut observer, notification);
+    }
+    loop {
+      let mut pending = lock(&self.pending);
+      match pending.pop_front() {
+        Some(notification) => {
+          drop(pending);
+          Self::accept(&mut observer, notification);
+        }
+        // Release the observer with the queue locked, a producer leaving a
+        // notification afterwards finds it released.
+        None => return drop(observer),
+      }
+    }
+  }
+
+  fn accept(
+    observer: &mut Option<BoxObserver<Item, Err>>,
+    notification: Notification<Arc<Item>, Err>,
+  ) {
+    let o = match observer {
+      Some(o) => o,
+      None => return,
+    };
+    match notification {
+      Notification::Next(value) => {
+        o.next(Arc::try_unwrap(value).unwrap_or_else(|v| Item::clone(&v)))
+      }
+      Notification::Error(err) => {
+        o.error(err);
+        *observer = None;
+      }
+      Notification::Complete => {
+        o.complete();
+        *observer = None;
+      }
+    }
+  }
+}
+
+impl<Item, Err> Clone for SharedSubject<Item, Err> {
+  #[inline]
+  fn clone(&self) -> Self { SharedSubject(self.0.clone()) }
+}
+
+impl<Item, Err> Default for SharedSubject<Item, Err> {
+  #[inline]
+  fn default() -> Self { Self::with_queue(None) }
+}
+
+impl<Item, Err> SharedSubject<Item, Err> {
+  /// Creates a subject emitting the values of concurrent producers
+  /// concurrently.
+  #[inline]
+  pub fn new() -> Self { Self::default() }
+
+  /// Creates a subject emitting the notifications of all the producers one
+  /// after another, in the order they were received.
+  #[inline]
+  pub fn ordered() -> Self {
+    Self::with_queue(Some(Mutex::new((VecDeque::new(), false))))
+  }
+
+  fn with_queue(queue: Option<Mutex<NotificationQueue<Item, Err>>>) -> Self {
+    SharedSubject(Arc::new(SharedInner {
+      observers: ArcSwap::from_pointee(vec![]),
+      stopped: Mutex::new(None),
+      is_stopped: AtomicBool::new(false),
+      closed: AtomicBool::new(false),
+      queue,
+    }))
+  }
+
+  /// Whether the subject completed or errored. A stopped subject ignores
+  /// further notifications, and observers subscribing to it receive the
+  /// completion or a clone of the error at once.
+  #[inline]
+  pub fn is_stopped(&self) -> bool { self.0.is_stopped.load(Ordering::Acquire) }
+
+  /// Whether the subject stopped with an error.
+  pub fn has_error(&self) -> bool {
+    matches!(*lock(&self.0.stopped), Some(Err(_)))
+  }
+
+  /// Whether the subject has observers which didn't unsubscribe yet.
+  pub fn observed(&self) -> bool {
+    self
+      .0
+      .observers
+      .load()
+      .iter()
+      .any(|o| !o.subscription.is_closed())
+  }
+}
+
+impl<Item: Clone, Err: Clone> SharedInner<Item, Err> {
+  fn dispatch(&self, notification: Notification<Arc<Item>, Err>) {
+    let queue = match &self.queue {
+      Some(queue) => queue,
+      None => return self.broadcast(notification),
+    };
+    {
+      let mut queue = lock(queue);
+      queue.0.push_back(notification);
+      if queue.1 {
+        return;
+      }
+      queue.1 = true;
+    }
+    let _emitting = Emitting(queue);
+    loop {
+      let notification = {
+        let mut queue = lock(queue);
+        match queue.0.pop_front() {
+          Some(notification) => notification,
+          None => {
+            queue.1 = false;
+            return;
+          }
+        }
+      };
+      self.broadcast(notification);
+    }
+  }
+
+  fn broadcast(&self, notification: Notification<Arc<Item>, Err>) {
+    match notification {
+      Notification::Next(value) => {
+        if self.is_stopped.load(Ordering::Acquire) {
+          return;
+        }
+        let observers = self.observers.load();
+        let mut any_closed = false;
+        let mut deliver = |o: &SharedObserver<Item, Err>, value| {
+          if !o.subscription.is_closed() {
+            o.deliver(Notification::Next(value));
+          }
+          any_closed |= o.subscription.is_closed();
+        };
+        // The last observer gets the value itself, it takes it if the others
+        // already received theirs.
+        if let Some((last, others)) = observers.split_last() {
+          others.iter().for_each(|o| deliver(o, value.clone()));
+          deliver(last, value);
+        }
+        if any_closed {
+          self.observers.rcu(|observers| {
+            observers
+              .iter()
+              .filter(|o| !o.subscription.is_closed())
+              .cloned()
+              .collect::<Vec<_>>()
+          });
+        }
+      }
+      Notification::Error(err) => self.stop(Err(err)),
+      Notification::Complete => self.stop(Ok(())),
+    }
+  }
+
+  fn stop(&self, stopped: Result<(), Err>) {
+    let observers = {
+      let mut state = lock(&self.stopped);
+      if state.is_some() {
+        return;
+      }
+      *state = Some(stopped.clone());
+      self.is_stopped.store(true, Ordering::Release);
+      self.observers.swap(Arc::new(vec![]))
+    };
+    observers.iter().for_each(|o| match &stopped {
+      Ok(()) => o.deliver(Notification::Complete),
+      Err(err) => o.deliver(Notification::Error(err.clone())),
+    });
+  }
+}
+
+impl<Item: Clone, Err: Clone> Observer for SharedSubject<Item, Err> {
+  type Item = Item;
+  type Err = Err;
+
+  #[inline]
+  fn next(&mut self, value: Item) {
+    self.0.dispatch(Notification::Next(Arc::new(value)))
+  }
+
+  #[inline]
+  fn error(&mut self, err: Err) { self.0.dispatch(Notification::Error(err)) }
+
+  #[inline]
+  fn complete(&mut self) { self.0.dispatch(Notification::Complete) }
+}
+
+impl<Item, Err> Observable for SharedSubject<Item, Err> {
+  type Item = Item;
+  type Err = Err;
+}
+
+impl<Item, Err: Clone> SharedObservable for SharedSubject<Item, Err> {
+  type Unsub = MutArc<SingleSubscription>;
+  fn actual_subscribe<O>(self, mut observer: O) -> Self::Unsub
+  where
+    O: Observer<Item = Self::Item, Err = Self::Err> + Sync + Send + 'static,
+  {
+    let mut subscription = MutArc::own(SingleSubscription::default());
+    // Check and subscribe under one lock, or the subject could stop in
+    // between and never notify the observer.
+    let state = lock(&self.0.stopped);
+    if let Some(stopped) = state.clone() {
+      drop(state);
+      match stopped {
+        Ok(()) => observer.complete(),
+        Err(err) => observer.error(err),
+      }
+      subscription.unsubscribe();
+    } else if self.0.closed.load(Ordering::Acquire) {
+      subscription.unsubscribe();
+    } else {
+      let o = Arc::new(SharedObserver {
+        observer: Mutex::new(Some(Box::new(observer) as BoxObserver<_, _>)),
+        pending: Mutex::new(VecDeque::new()),
+        subscription: subscription.clone(),
+      });
+      self.0.observers.rcu(|observers| {
+        let mut observers = Vec::clone(observers);
+        observers.push(o.clone());
+        observers
+      });
+    }
+    subscription
+  }
+}
+
+impl<Item, Err> SubscriptionLike for SharedSubject<Item, Err> {
+  fn unsubscribe(&mut self) {
+    let _stopped = lock(&self.0.stopped);
+    self.0.closed.store(true, Ordering::Release);
+    self
+      .0
+      .observers
+      .swap(Arc::new(vec![]))
+      .iter()
+      .for_each(|o| o.subscription.clone().unsubscribe());
+  }
+
+  #[inline]
+  fn is_closed(&self) -> bool { self.0.closed.load(Ordering::Acquire) }
+}
+
+impl<Item, Err> TearDownSize for SharedSubject<Item, Err> {
+  #[inline]
+  fn teardown_size(&self) -> usize { self.0.observers.load().len() }
+}
+
+#[cfg(test)]
+mod test {
+  use crate::prelude::*;
+  use crate::test_observer::TestObserver;
+  use bencher::Bencher;
+  use std::{
+    panic::AssertUnwindSafe,
+    sync::{
+      Arc,
+      atomic::{AtomicUsize, Ordering},
+    },
+  };
+
+  #[test]
+  fn share_values() {
+    let mut subject = SharedSubject::<_, ()>::new();
+    let first = TestObserver::new();
+    let second = TestObserver::new();
+    subject.clone().actual_subscribe(first.clone());
+    subject.clone().actual_subscribe(second.clone());
+    subject.next(Arc::new(1));
+    subject.complete();
+    subject.next(Arc::new(2));
+
+    first.assert_values(&[Arc::new(1)]).assert_complete();
+    second.assert_values(&[Arc::new(1)]).assert_complete();
+    let (a, b) = (first.values(), second.values());
+    assert!(Arc::ptr_eq(&a[0], &b[0]));
+  }
+
+  #[test]
+  fn clone_for_all_but_last() {
+    struct Counted(Arc<AtomicUsize>);
+    impl Clone for Counted {
+      fn clone(&self) -> Self {
+        self.0.fetch_add(1, Ordering::Relaxed);
+        Counted(self.0.clone())
+      }
+    }
+
+    let mut subject = SharedSubject::<_, ()>::new();
+    (0..3).for_each(|_| {
+      subject.clone().actual_subscribe(TestObserver::new());
+    });
+    let clones = Arc::new(AtomicUsize::new(0));
+    subject.next(Counted(clones.clone()));
+    assert_eq!(clones.load(Ordering::Relaxed), 2);
+  }
+
+  #[test]
+  fn replay_stop() {
+    let mut subject = SharedSubject::<i32, _>::new();
+    subject.error("oops");
+    assert!(subject.is_stopped());
+    assert!(subject.has_error());
+
+    let observer = TestObserver::new();
+    let subscription = subject.clone().actual_subscribe(observer.clone());
+    observer.assert_error_matches(|e| *e == "oops");
+    assert!(subscription.is_closed());
+  }
+
+  #[test]
+  fn unsubscribe() {
+    let mut subject = SharedSubject::<_, ()>::new();
+    let first = TestObserver::new();
+    let second = TestObserver::new();
+    let mut subscription = subject.clone().actual_subscribe(first.clone());
+    subject.clone().actual_subscribe(second.clone());
+    subject.next(1);
+    subscription.unsubscribe();
+    subject.next(2);
+
+    first.assert_values(&[1]);
+    second.assert_values(&[1, 2]);
+    assert_eq!(subject.teardown_size(), 1);
+
+    subject.unsubscribe();
+    subject.next(3);
+    second.assert_values(&[1, 2]);
+    assert!(!subject.observed());
+  }
+
+  #[test]
+  fn ordered_reentrant() {
+    let subject = SharedSubject::<_, ()>::ordered();
+    let observer = TestObserver::new();
+    let mut c_subject = subject.clone();
+    subject.clone().into_shared().subscribe(move |v| {
+      if v < 3 {
+        c_subject.next(v + 1);
+      }
+    });
+    subject.clone().actual_subscribe(observer.clone());
+    subject.clone().next(0);
+
+    observer.assert_values(&[0, 1, 2, 3]);
+  }
+
+  #[test]
+  fn unordered_reentrant() {
+    let subject = SharedSubject::<_, ()>::new();
+    let reentrant = TestObserver::<_, ()>::new();
+    let observer = TestObserver::new();
+    let (mut c_subject, c_reentrant) = (subject.clone(), reentrant.clone());
+    subject.clone().into_shared().subscribe(move |v| {
+      c_reentrant.clone().next(v);
+      if v < 3 {
+        c_subject.next(v + 1);
+      }
+    });
+    subject.clone().actual_subscribe(observer.clone());
+    subject.clone().next(0);
+
+    reentrant.assert_values(&[0, 1, 2, 3]);
+    let mut values = observer.values();
+    values.sort_unstable();
+    assert_eq!(values, vec![0, 1, 2, 3]);
+  }
+
+  #[test]
+  fn drop_panicking_observer() {
+    for mut subject in [SharedSubject::new(), SharedSubject::ordered()] {
+      let observer = TestObserver::<_, ()>::new();
+      let subscription = subject
+        .clone()
+        .into_shared()
+        .subscribe(|v| assert_ne!(v, 1));
+      subject.clone().actual_subscribe(observer.clone());
+
+      let mut c_subject = subject.clone();
+      let result =
+        std::panic::catch_unwind(AssertUnwindSafe(|| c_subject.next(1)));
+      assert!(result.is_err());
+      subject.next(2);
+
+      assert!(subscription.is_closed());
+      assert_eq!(subject.teardown_size(), 1);
+      subject.complete();
+      observer.assert_values(&[2]).assert_complete();
+    }
+  }
+
+  #[test]
+  fn concurrent_producers() {
+    for subject in [SharedSubject::new(), SharedSubject::ordered()] {
+      let observer = TestObserver::<_, ()>::new();
+      subject.clone().actual_subscribe(observer.clone());
+      produce(&subject, 4, 100, |v| v);
+
+      let mut values = observer.values();
+      values.sort_unstable();
+      assert_eq!(values, (0..400).collect::<Vec<_>>());
+    }
+  }
+
+  /// Emits `count` values from each of `producers` threads.
+  fn produce<S, T>(
+    subject: &S,
+    producers: usize,
+    count: usize,
+    value: fn(usize) -> T,
+  ) where
+    S: Observer<Item = T> + Clone + Send + 'static,
+    T: 'static,
+  {
+    let handles = (0..producers)
+      .map(|i| {
+        let mut subject = subject.clone();
+        std::thread::spawn(move || {
+          (0..count).for_each(|v| subject.next(value(i * count + v)))
+        })
+      })
+      .collect::<Vec<_>>();
+    handles.into_iter().for_each(|h| h.join().unwrap());
+  }
+
+  #[test]
+  fn bench() { do_bench(); }
+
+  benchmark_group!(
+    do_bench,
+    bench_shared_subject,
+    bench_ordered_shared_subject,
+    bench_shared_subject_of_arc
+  );
+
+  const PRODUCERS: usize = 4;
+  const VALUES: usize = 250;
+  const OBSERVERS: usize = 8;
+
+  fn payload(v: usize) -> Vec<usize> { vec![v; 16] }
+
+  fn bench_shared_subject(b: &mut Bencher) {
+    b.iter(|| broadcast(SharedSubject::new(), payload))
+  }
+
+  fn bench_ordered_shared_subject(b: &mut Bencher) {
+    b.iter(|| broadcast(SharedSubject::ordered(), payload))
+  }
+
+  fn bench_shared_subject_of_arc(b: &mut Bencher) {
+    b.iter(|| broadcast(SharedSubject::new(), |v| Arc::new(payload(v))))
+  }
+
+  fn broadcast<T>(subject: SharedSubject<T, ()>, value: fn(usize) -> T)
+  where
+    T: Clone + Send + Sync + 'static,
+  {
+    (0..OBSERVERS).for_each(|_| {
+      subject.clone().into_shared().subscribe(|v: T| {
+        bencher::black_box(v);
+      });
+    });
+    produce(&subject, PRODUCERS, VALUES, value);
+  }
+}
@@ -0,0 +1,202 @@
+use crate::prelude::*;
+use std::sync::Arc;
+
+/// The emitting half of a subject split by [`Subject::split`], it can only
+/// notify the observers of the subject.
+///
+/// Like a channel sender it can be cloned. An emitter split with
+/// [`split_with`](Subject::split_with) and `complete_on_drop` completes the
+/// subject once it and all its clones are dropped.
+pub struct SubjectEmitter<S: Observer> {
+  subject: S,
+  drop_guard: Option<Arc<()>>,
+}
+
+/// The subscribing half of a subject split by [`Subject::split`], it can only
+/// be subscribed to.
+#[derive(Clone)]
+pub struct SubjectReceiver<S>(S);
+
+macro_rules! impl_split {
+  (impl<$($g: tt),*> $ty: ty) => {
+    impl<$($g),*> $ty
+    where
+      Self: Observer + Clone,
+    {
+      /// Splits the subject into an emitter, which can only notify its
+      /// observers, and a receiver, which can only be subscribed to.
+      ///
+      /// ```
+      /// use rxrust::prelude::*;
+      ///
+      /// let (mut emitter, receiver) = LocalSubject::<i32, ()>::new().split();
+      /// let sum = MutRc::own(0);
+      /// let c_sum = sum.clone();
+      /// receiver.subscribe(move |v| *c_sum.rc_deref_mut() += v);
+      ///
+      /// emitter.next(1);
+      /// emitter.next(2);
+      /// assert_eq!(*sum.rc_deref(), 3);
+      /// ```
+      pub fn split(self) -> (SubjectEmitter<Self>, SubjectReceiver<Self>) {
+        self.split_with(false)
+      }
+
+      /// Splits the subject like [`split`](Self::split), the subject
+      /// completes when the emitter and all its clones are dropped if
+      /// `complete_on_drop` is true.
+      ///
+      /// ```
+      /// use rxrust::prelude::*;
+      ///
+      /// let (emitter, receiver) =
+      ///   LocalSubject::<i32, ()>::new().split_with(true);
+      /// let completed = MutRc::own(false);
+      /// let c_completed = completed.clone();
+      /// receiver.subscribe_all(|_| {}, |_| {}, move || {
+      ///   *c_completed.rc_deref_mut() = true
+      /// });
+      ///
+      /// drop(emitter);
+      /// assert!(*completed.rc_deref());
+      /// ```
+      pub fn split_with(
+        self,
+        complete_on_drop: bool,
+      ) -> (SubjectEmitter<Self>, SubjectReceiver<Self>) {
+        let emitter = SubjectEmitter {
+          subject: self.clone(),
+          drop_guard: complete_on_drop.then(|| Arc::new(())),
+        };
+        (emitter, SubjectReceiver(self))
+      }
+    }
+  };
+}
+
+impl_split!(impl<T, B> Subject<T, B>);
+impl_split!(impl<Item, Err> SharedSubject<Item, Err>);
+
+impl<S: Observer + Clone> Clone for SubjectEmitter<S> {
+  fn clone(&self) -> Self {
+    SubjectEmitter {
+      subject: self.subject.clone(),
+      drop_guard: self.drop_guard.clone(),
+    }
+  }
+}
+
+impl<S: Observer> Observer for SubjectEmitter<S> {
+  type Item = S::Item;
+  type Err = S::Err;
+
+  #[inline]
+  fn next(&mut self, value: Self::Item) { self.subject.next(value) }
+
+  #[inline]
+  fn error(&mut self, err: Self::Err) { self.subject.error(err) }
+
+  #[inline]
+  fn complete(&mut self) { self.subject.complete() }
+}
+
+impl<S: Observer> Drop for SubjectEmitter<S> {
+  fn drop(&mut self) {
+    let last = self.drop_guard.take().and_then(Arc::into_inner);
+    if last.is_some() {
+      self.subject.complete();
+    }
+  }
+}
+
+impl<S: Observable> Observable for SubjectReceiver<S> {
+  type Item = S::Item;
+  type Err = S::Err;
+}
+
+impl<'a, S: LocalObservable<'a>> LocalObservable<'a> for SubjectReceiver<S> {
+  type Unsub = S::Unsub;
+
+  #[inline]
+  fn actual_subscribe<O>(self, observer: O) -> Self::Unsub
+  where
+    O: Observer<Item = Self::Item, Err = Self::Err> + 'a,
+  {
+    self.0.actual_subscribe(observer)
+  }
+}
+
+impl<S: SharedObservable> SharedObservable for SubjectReceiver<S> {
+  type Unsub = S::Unsub;
+
+  #[inline]
+  fn actual_subscribe<O>(self, observer: O) -> Self::Unsub
+  where
+    O: Observer<Item = Self::Item, Err = Self::Err> + Sync + Send + 'static,
+  {
+    self.0.actual_subscribe(observer)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use crate::prelude::*;
+  use crate::test_observer::TestObserver;
+
+  #[test]
+  fn emit_through_emitter() {
+    let observer = TestObserver::<i32, ()>::new();
+    let (mut emitter, receiver) = LocalSubject::new().split();
+    receiver.clone().actual_subscribe(observer.clone());
+    emitter.next(1);
+    emitter.next(2);
+    emitter.complete();
+
+    observer.assert_values(&[1, 2]).assert_complete();
+  }
+
+  #[test]
+  fn keep_open_on_drop() {
+    let observer = TestObserver::<i32, ()>::new();
+    let (emitter, receiver) = LocalSubject::new().split();
+    receiver.actual_subscribe(observer.clone());
+    drop(emitter);
+
+    observer.assert_not_complete();
+  }
+
+  #[test]
+  fn complete_when_last_clone_dropped() {
+    let observer = TestObserver::<i32, ()>::new();
+    let (emitter, receiver) = LocalSubject::new().split_with(true);
+    receiver.actual_subscribe(observer.clone());
+
+    let mut clone = emitter.clone();
+    drop(emitter);
+    observer.assert_not_complete();
+    clone.next(1);
+    drop(clone);
+
+    observer.assert_values(&[1]).assert_complete();
+  }
+
+  #[cfg(not(target_arch = "wasm32"))]
+  #[test]
+  fn shared() {
+    let observer = TestObserver::<i32, ()>::new();
+    let (emitter, receiver) = SharedSubject::new().split_with(true);
+    receiver.into_shared().actual_subscribe(observer.clone());
+
+    let handles = (0..4)
+      .map(|i| {
+        let mut emitter = emitter.clone();
+        std::thread::spawn(move || emitter.next(i))
+      })
+      .collect::<Vec<_>>();
+    drop(emitter);
+    handles.into_iter().for_each(|h| h.join().unwrap());
+
+    observer.assert_complete();
+    assert_eq!(observer.value_count(), 4);
+  }
+}
@@ -0,0 +1,529 @@
+use crate::prelude::*;
+use std::{collections::HashMap, hash::Hash};
+
+/// A keyed broker routing the values published to a topic to the observers of
+/// that topic.
+///
+/// Each topic is a subject of its own, created when the first observer
+/// subscribes to it and dropped once all its observers unsubscribed. Observers
+/// can also subscribe to all the topics matching a predicate, to match topics
+/// by prefix or by wildcard.
+///
+/// ```
+/// use rxrust::prelude::*;
+///
+/// let broker = LocalTopicSubject::<&str, i32, ()>::new();
+/// let orders = MutRc::own(vec![]);
+/// let all = MutRc::own(vec![]);
+/// let (c_orders, c_all) = (orders.clone(), all.clone());
+/// broker
+///   .subscribe_topic("orders.new")
+///   .subscribe(move |v| c_orders.rc_deref_mut().push(v));
+/// broker
+///   .subscribe_matching(|topic: &&str| topic.starts_with("orders."))
+///   .subscribe(move |v| c_all.rc_deref_mut().push(v));
+///
+/// broker.publish(&"orders.new", 1);
+/// broker.publish(&"orders.paid", 2);
+/// broker.publish(&"users.new", 3);
+/// assert_eq!(*orders.rc_deref(), vec![1]);
+/// assert_eq!(*all.rc_deref(), vec![1, 2]);
+/// ```
+#[derive(Clone, Default)]
+pub struct TopicSubject<R>(R);
+
+/// The topics of a [`TopicSubject`] and the subjects of the observers
+/// subscribed by a predicate.
+pub struct TopicState<K, S, P> {
+  topics: HashMap<K, S>,
+  patterns: Vec<(P, S)>,
+  closed: bool,
+}
+
+/// The topics a [`TopicObservable`] subscribes to.
+#[derive(Clone)]
+pub enum TopicFilter<K, F> {
+  /// The topic equal to the key.
+  Topic(K),
+  /// All the topics matching the predicate.
+  Matching(F),
+}
+
+/// The subscription of an observer of a [`TopicObservable`], which drops its
+/// topic or predicate from the [`TopicSubject`] once it unsubscribed the last
+/// observer of it.
+pub struct TopicSubscription<R, U> {
+  subject: TopicSubject<R>,
+  subscription: U,
+}
+
+/// An observable of the values published to some topics of a
+/// [`TopicSubject`].
+#[derive(Clone)]
+pub struct TopicObservable<S, K, F> {
+  subject: S,
+  filter: TopicFilter<K, F>,
+}
+
+type LocalPredicate<'a, K> = Box<dyn Fn(&K) -> bool + 'a>;
+type SharedPredicate<K> = Box<dyn Fn(&K) -> bool + Send + Sync>;
+type LocalTopicState<'a, K, Item, Err> =
+  TopicState<K, LocalSubject<'a, Item, Err>, LocalPredicate<'a, K>>;
+type SharedTopicState<K, Item, Err> =
+  TopicState<K, SharedSubject<Item, Err>, SharedPredicate<K>>;
+
+pub type LocalTopicSubject<'a, K, Item, Err> =
+  TopicSubject<MutRc<LocalTopicState<'a, K, Item, Err>>>;
+
+pub type SharedTopicSubject<K, Item, Err> =
+  TopicSubject<MutArc<SharedTopicState<K, Item, Err>>>;
+
+impl<K, S, P> Default for TopicState<K, S, P> {
+  fn default() -> Self {
+    TopicState {
+      topics: HashMap::new(),
+      patterns: vec![],
+      closed: false,
+    }
+  }
+}
+
+impl<'a, K, Item, Err> LocalTopicSubject<'a, K, Item, Err> {
+  #[inline]
+  pub fn new() -> Self { Self::default() }
+}
+
+impl<K, Item, Err> SharedTopicSubject<K, Item, Err> {
+  #[inline]
+  pub fn new() -> Self { Self::default() }
+}
+
+macro_rules! impl_topic_subject {
+  (impl<$($g: tt),*> $ty: ty) => {
+    impl<$($g),*> $ty
+    where
+      K: Hash + Eq,
+      Item: Clone,
+      Err: Clone,
+    {
+      /// Returns an observable of the values published to `key`.
+      pub fn subscribe_topic(
+        &self,
+        key: K,
+      ) -> TopicObservable<Self, K, fn(&K) -> bool> {
+        TopicObservable {
+          subject: self.clone(),
+          filter: TopicFilter::Topic(key),
+        }
+      }
+
+      /// Returns an observable of the values published to all the topics
+      /// matching `predicate`.
+      pub fn subscribe_matching<F>(
+        &self,
+        predicate: F,
+      ) -> TopicObservable<Self, K, F>
+      where
+        F: Fn(&K) -> bool,
+      {
+        TopicObservable {
+          subject: self.clone(),
+          filter: TopicFilter::Matching(predicate),
+        }
+      }
+
+      /// Emits `value` to the observers of the topic `key`, and to the
+      /// observers of the predicates matching it.
+      pub fn publish(&self, key: &K, value: Item) {
+        let subjects = {
+          let state = self.0.rc_deref();
+          let matching = state
+            .patterns
+            .iter()
+            .filter(|(predicate, _)| predicate(key))
+            .map(|(_, subject)| subject);
+          let topic = state.topics.get(key).into_iter();
+          topic.chain(matching).cloned().collect::<Vec<_>>()
+        };
+        if subjects.is_empty() {
+          return;
+        }
+        subjects
+          .into_iter()
+          .for_each(|mut subject| subject.next(value.clone()));
+
+        let mut state = self.0.rc_deref_mut();
+        if state.topics.get(key).map_or(false, |s| !s.observed()) {
+          state.topics.remove(key);
+        }
+        state.patterns.retain(|(_, subject)| subject.observed());
+      }
+
+      /// The number of topics with observers.
+      pub fn topic_count(&self) -> usize {
+        let mut state = self.0.rc_deref_mut();
+        state.cleanup();
+        state.topics.len()
+      }
+    }
+  };
+}
+
+impl_topic_subject!(impl<'a, K, Item, Err> LocalTopicSubject<'a, K, Item, Err>);
+impl_topic_subject!(impl<K, Item, Err> SharedTopicSubject<K, Item, Err>);
+
+macro_rules! impl_topic_state {
+  (impl<$($g: tt),*> $ty: ty, $subject: ty) => {
+    impl<$($g),*> $ty
+    where
+      K: Hash + Eq,
+    {
+      /// Drops the topics and the predicates without observers, and returns
+      /// their subjects.
+      fn cleanup(&mut self) -> Vec<$subject> {
+        let mut unobserved = vec![];
+        let mut observed = |subject: &$subject| {
+          let observed = subject.observed();
+          if !observed {
+            unobserved.push(subject.clone());
+          }
+          observed
+        };
+        self.topics.retain(|_, subject| observed(subject));
+        self.patterns.retain(|(_, subject)| observed(subject));
+        unobserved
+      }
+    }
+  };
+}
+
+impl_topic_state!(
+  impl<'a, K, Item, Err> LocalTopicState<'a, K, Item, Err>,
+  LocalSubject<'a, Item, Err>
+);
+impl_topic_state!(
+  impl<K, Item, Err> SharedTopicState<K, Item, Err>,
+  SharedSubject<Item, Err>
+);
+
+macro_rules! impl_topic_subscription {
+  (impl<$($g: tt),*> $ty: ty) => {
+    impl<$($g),*> SubscriptionLike for $ty
+    where
+      K: Hash + Eq,
+    {
+      fn unsubscribe(&mut self) {
+        self.subscription.unsubscribe();
+        // Drop the subjects once the state is released, their observers may
+        // unsubscribe from the topic subject when they are dropped.
+        let unobserved = self.subject.0.rc_deref_mut().cleanup();
+        drop(unobserved);
+      }
+
+      #[inline]
+      fn is_closed(&self) -> bool { self.subscription.is_closed() }
+    }
+  };
+}
+
+impl_topic_subscription!(
+  impl<'a, K, Item, Err> TopicSubscription<
+    MutRc<LocalTopicState<'a, K, Item, Err>>,
+    MutRc<SingleSubscription>,
+  >
+);
+impl_topic_subscription!(
+  impl<K, Item, Err> TopicSubscription<
+    MutArc<SharedTopicState<K, Item, Err>>,
+    MutArc<SingleSubscription>,
+  >
+);
+
+impl<K, S, P> SubscriptionLike for TopicState<K, S, P>
+where
+  S: SubscriptionLike,
+{
+  fn unsubscribe(&mut self) {
+    self.closed = true;
+    self
+      .topics
+      .drain()
+      .map(|(_, subject)| subject)
+      .chain(self.patterns.drain(..).map(|(_, subject)| subject))
+      .for_each(|mut subject| subject.unsubscribe());
+  }
+
+  #[inline]
+  fn is_closed(&self) -> bool { self.closed }
+}
+
+impl<K, S, P> TearDownSize for TopicState<K, S, P>
+where
+  S: TearDownSize,
+{
+  fn teardown_size(&self) -> usize {
+    self
+      .topics
+      .values()
+      .chain(self.patterns.iter().map(|(_, subject)| subject))
+      .map(TearDownSize::teardown_size)
+      .sum()
+  }
+}
+
+impl<R: SubscriptionLike> SubscriptionLike for TopicSubject<R> {
+  #[inline]
+  fn unsubscribe(&mut self) { self.0.unsubscribe() }
+
+  #[inline]
+  fn is_closed(&self) -> bool { self.0.is_closed() }
+}
+
+impl<R: TearDownSize> TearDownSize for TopicSubject<R> {
+  #[inline]
+  fn teardown_size(&self) -> usize { self.0.teardown_size() }
+}
+
+impl<'a, K, Item, Err, F> Observable
+  for TopicObservable<LocalTopicSubject<'a, K, Item, Err>, K, F>
+{
+  type Item = Item;
+  type Err = Err;
+}
+
+impl<'a, K, Item, Err, F> LocalObservable<'a>
+  for TopicObservable<LocalTopicSubject<'a, K, Item, Err>, K, F>
+where
+  K: Hash + Eq,
+  Err: Clone,
+  F: Fn(&K) -> bool + 'a,
+{
+  type Unsub = TopicSubscription<
+    MutRc<LocalTopicState<'a, K, Item, Err>>,
+    MutRc<SingleSubscription>,
+  >;
+  fn actual_subscribe<O>(self, observer: O) -> Self::Unsub
+  where
+    O: Observer<Item = Self::Item, Err = Self::Err> + 'a,
+  {
+    let mut state = self.subject.0.rc_deref_mut();
+    if state.closed {
+      let mut subscription = MutRc::own(SingleSubscription::default());
+      subscription.unsubscribe();
+      drop(state);
+      return TopicSubscription {
+        subject: self.subject,
+        subscription,
+      };
+    }
+    state.cleanup();
+    let subject = match self.filter {
+      TopicFilter::Topic(key) => state.topics.entry(key).or_default().clone(),
+      TopicFilter::Matching(predicate) => {
+        let subject = LocalSubject::new();
+        state.patterns.push((Box::new(predicate), subject.clone()));
+        subject
+      }
+    };
+    drop(state);
+    TopicSubscription {
+      subscription: subject.actual_subscribe(observer),
+      subject: self.subject,
+    }
+  }
+}
+
+impl<K, Item, Err, F> Observable
+  for TopicObservable<SharedTopicSubject<K, Item, Err>, K, F>
+{
+  type Item = Item;
+  type Err = Err;
+}
+
+impl<K, Item, Err, F> SharedObservable
+  for TopicObservable<SharedTopicSubject<K, Item, Err>, K, F>
+where
+  K: Hash + Eq + Send + 'static,
+  Item: Send + Sync + 'static,
+  Err: Clone + Send + 'static,
+  F: Fn(&K) -> bool + Send + Sync + 'static,
+{
+  type Unsub = TopicSubscription<
+    MutArc<SharedTopicState<K, Item, Err>>,
+    MutArc<SingleSubscription>,
+  >;
+  fn actual_subscribe<O>(self, observer: O) -> Self::Unsub
+  where
+    O: Observer<Item = Self::Item, Err = Self::Err> + Sync + Send + 'static,
+  {
+    let mut state = self.subject.0.rc_deref_mut();
+    if state.closed {
+      let mut subscription = MutArc::own(SingleSubscription::default());
+      subscription.unsubscribe();
+      drop(state);
+      return TopicSubscription {
+        subject: self.subject,
+        subscription,
+      };
+    }
+    state.cleanup();
+    let subject = match self.filter {
+      TopicFilter::Topic(key) => state.topics.entry(key).or_default().clone(),
+      TopicFilter::Matching(predicate) => {
+        let subject = SharedSubject::new();
+        state.patterns.push((Box::new(predicate), subject.clone()));
+        subject
+      }
+    };
+    drop(state);
+    TopicSubscription {
+      subscription: subject.actual_subscribe(observer),
+      subject: self.subject,
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use crate::prelude::*;
+  use crate::test_observer::TestObserver;
+
+  #[test]
+  fn route_by_topic() {
+    let broker = LocalTopicSubject::<_, _, ()>::new();
+    let a = TestObserver::new();
+    let b = TestObserver::new();
+    broker.subscribe_topic("a").actual_subscribe(a.clone());
+    broker.subscribe_topic("b").actual_subscribe(b.clone());
+
+    broker.publish(&"a", 1);
+    broker.publish(&"b", 2);
+    broker.publish(&"c", 3);
+
+    a.assert_values(&[1]);
+    b.assert_values(&[2]);
+  }
+
+  #[test]
+  fn match_by_predicate() {
+    let broker = LocalTopicSubject::<String, _, ()>::new();
+    let prefix = TestObserver::new();
+    let wildcard = TestObserver::new();
+    broker
+      .subscribe_matching(|k: &String| k.starts_with("orders."))
+      .actual_subscribe(prefix.clone());
+    broker
+      .subscribe_matching(|_: &String| true)
+      .actual_subscribe(wildcard.clone());
+
+    broker.publish(&"orders.new".to_string(), 1);
+    broker.publish(&"users.new".to_string(), 2);
+
+    prefix.assert_values(&[1]);
+    wildcard.assert_values(&[1, 2]);
+  }
+
+  #[test]
+  fn drop_topics_without_observers() {
+    let broker = LocalTopicSubject::<_, i32, ()>::new();
+    let mut a = broker.subscribe_topic("a").subscribe(|_| {});
+    let mut b = broker.subscribe_topic("b").subscribe(|_| {});
+    broker.subscribe_topic("b").subscribe(|_| {});
+    assert_eq!(broker.topic_count(), 2);
+    assert_eq!(broker.teardown_size(), 3);
+
+    a.unsubscribe();
+    b.unsubscribe();
+    broker.publish(&"b", 1);
+    assert_eq!(broker.topic_count(), 1);
+    assert_eq!(broker.teardown_size(), 1);
+  }
+
+  #[test]
+  fn drop_topics_on_unsubscribe() {
+    let broker = LocalTopicSubject::<_, i32, ()>::new();
+    let mut a = broker.subscribe_topic("a").subscribe(|_| {});
+    let mut b1 = broker.subscribe_topic("b").subscribe(|_| {});
+    let mut b2 = broker.subscribe_topic("b").subscribe(|_| {});
+    let mut matching =
+      broker.subscribe_matching(|_: &&str| true).subscribe(|_| {});
+    assert_eq!(broker.teardown_size(), 4);
+
+    a.unsubscribe();
+    assert_eq!(broker.teardown_size(), 3);
+    matching.unsubscribe();
+    assert_eq!(broker.teardown_size(), 2);
+    b1.unsubscribe();
+    assert_eq!(broker.topic_count(), 1);
+    b2.unsubscribe();
+    assert_eq!(broker.teardown_size(), 0);
+    assert_eq!(broker.topic_count(), 0);
+  }
+
+  #[cfg(not(target_arch = "wasm32"))]
+  #[test]
+  fn shared_drop_topics_on_unsubscribe() {
+    let broker = SharedTopicSubject::<_, i32, ()>::new();
+    let mut a = broker.subscribe_topic("a").into_shared().subscribe(|_| {});
+    assert_eq!(broker.teardown_size(), 1);
+    a.unsubscribe();
+    assert_eq!(broker.teardown_size(), 0);
+    assert_eq!(broker.topic_count(), 0);
+  }
+
+  #[test]
+  fn reentrant_publish() {
+    let broker = LocalTopicSubject::<_, _, ()>::new();
+    let observer = TestObserver::new();
+    let c_broker = broker.clone();
+    broker.subscribe_topic("a").subscribe(move |v| {
+      if v < 3 {
+        c_broker.publish(&"b", v + 1);
+      }
+    });
+    broker
+      .subscribe_topic("b")
+      .actual_subscribe(observer.clone());
+    broker.publish(&"a", 1);
+
+    observer.assert_values(&[2]);
+  }
+
+  #[test]
+  fn unsubscribe() {
+    let mut broker = LocalTopicSubject::<_, i32, ()>::new();
+    let observer = TestObserver::new();
+    broker
+      .subscribe_topic("a")
+      .actual_subscribe(observer.clone());
+    broker.unsubscribe();
+    broker.publish(&"a", 1);
+
+    observer.assert_values(&[]);
+    assert!(broker.is_closed());
+    assert!(broker.subscribe_topic("a").subscribe(|_| {}).is_closed());
+  }
+
+  #[cfg(not(target_arch = "wasm32"))]
+  #[test]
+  fn shared() {
+    let broker = SharedTopicSubject::<_, _, ()>::new();
+    let observer = TestObserver::new();
+    broker
+      .subscribe_topic(1)
+      .into_shared()
+      .actual_subscribe(observer.clone());
+
+    let handles = (0..4)
+      .map(|i| {
+        let broker = broker.clone();
+        std::thread::spawn(move || broker.publish(&(i % 2), i))
+      })
+      .collect::<Vec<_>>();
+    handles.into_iter().for_each(|h| h.join().unwrap());
+
+    let mut values = observer.values();
+    values.sort_unstable();
+    assert_eq!(values, vec![1, 3]);
+  }
+}
@@ -0,0 +1,354 @@
+use crate::prelude::*;
+use std::{collections::VecDeque, fmt};
+
+/// A subject allowing a single observer, it buffers all the notifications it
+/// receives until that observer subscribes, then replays them to it and passes
+/// the next ones through.
+///
+/// Observers subscribing after the first one receive a [`UnicastError`], so
+/// its error type has to be convertible from it.
+///
+/// ```
+/// use rxrust::prelude::*;
+///
+/// let mut subject = LocalUnicastSubject::<i32, UnicastError>::new();
+/// subject.next(1);
+/// subject.next(2);
+///
+/// let values = MutRc::own(vec![]);
+/// let c_values = values.clone();
+/// subject.clone().subscribe_err(
+///   move |v| c_values.rc_deref_mut().push(v),
+///   |err: UnicastError| panic!("{}", err),
+/// );
+/// subject.next(3);
+/// assert_eq!(*values.rc_deref(), vec![1, 2, 3]);
+/// ```
+#[derive(Clone, Default)]
+pub struct UnicastSubject<V>(V);
+
+/// The buffer of an [`UnicastSubject`] and its observer.
+pub struct UnicastState<O: Observer + ?Sized, U> {
+  buffer: VecDeque<Notification<O::Item, O::Err>>,
+  consumer: Consumer<Box<O>, U>,
+  stopped: bool,
+}
+
+enum Consumer<O, U> {
+  /// No observer subscribed yet.
+  Pending,
+  /// The observer waits for notifications.
+  Ready(O, U),
+  /// The observer is receiving notifications, the new ones are buffered.
+  Busy,
+  /// The observer stopped or unsubscribed.
+  Detached,
+}
+
+/// The error an observer subscribing to an [`UnicastSubject`] which already has
+/// an observer receives.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UnicastError;
+
+impl fmt::Display for UnicastError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str("unicast subject already has an observer")
+  }
+}
+
+impl std::error::Error for UnicastError {}
+
+pub type LocalUnicastSubject<'a, Item, Err> = UnicastSubject<
+  MutRc<
+    UnicastState<
+      dyn Observer<Item = Item, Err = Err> + 'a,
+      MutRc<SingleSubscription>,
+    >,
+  >,
+>;
+
+pub type SharedUnicastSubject<Item, Err> = UnicastSubject<
+  MutArc<
+    UnicastState<
+      dyn Observer<Item = Item, Err = Err> + Send + Sync,
+      MutArc<SingleSubscription>,
+    >,
+  >,
+>;
+
+impl<'a, Item, Err> LocalUnicastSubject<'a, Item, Err> {
+  #[inline]
+  pub fn new() -> Self { Self::default() }
+}
+
+impl<Item, Err> SharedUnicastSubject<Item, Err> {
+  #[inline]
+  pub fn new() -> Self { Self::default() }
+}
+
+impl<O: Observer + ?Sized, U> Default for UnicastState<O, U> {
+  fn default() -> Self {
+    UnicastState {
+      buffer: VecDeque::new(),
+      consumer: Consumer::Pending,
+      stopped: false,
+    }
+  }
+}
+
+impl<O: Observer + ?Sized, U> UnicastState<O, U> {
+  /// Buffers `notification`, returns the observer if it's ready to receive it.
+  fn push(
+    &mut self,
+    notification: Notification<O::Item, O::Err>,
+  ) -> Option<(Box<O>, U)> {
+    if self.stopped || matches!(self.consumer, Consumer::Detached) {
+      return None;
+    }
+    self.stopped = !matches!(notification, Notification::Next(_));
+    self.buffer.push_back(notification);
+    match std::mem::replace(&mut self.consumer, Consumer::Busy) {
+      Consumer::Ready(observer, subscription) => Some((observer, subscription)),
+      consumer => {
+        self.consumer = consumer;
+        None
+      }
+    }
+  }
+
+  /// Marks the subject as observed, returns false if it already was.
+  fn accept(&mut self) -> bool {
+    let pending = matches!(self.consumer, Consumer::Pending);
+    if pending {
+      self.consumer = Consumer::Busy;
+    }
+    pending
+  }
+}
+
+macro_rules! impl_unicast {
+  ($rc: ident) => {
+    impl<O, U> UnicastSubject<$rc<UnicastState<O, U>>>
+    where
+      O: Observer + ?Sized,
+      U: SubscriptionLike,
+    {
+      /// Emits the buffered notifications to `observer`, until the buffer is
+      /// empty and the observer can wait for the next ones.
+      fn drain(&self, mut observer: Box<O>, subscription: U) {
+        loop {
+          let notification = {
+            let mut state = self.0.rc_deref_mut();
+            let notification = if subscription.is_closed() {
+              None
+            } else {
+              state.buffer.pop_front()
+            };
+            match notification {
+              Some(notification) => notification,
+              None if subscription.is_closed() || state.stopped => {
+                state.buffer.clear();
+                state.consumer = Consumer::Detached;
+                return;
+              }
+              None => {
+                state.consumer = Consumer::Ready(observer, subscription);
+                return;
+              }
+            }
+          };
+          notification.accept(&mut *observer);
+        }
+      }
+
+      fn notify(&mut self, notification: Notification<O::Item, O::Err>) {
+        let ready = self.0.rc_deref_mut().push(notification);
+        if let Some((observer, subscription)) = ready {
+          self.drain(observer, subscription);
+        }
+      }
+    }
+
+    impl<O, U> Observer for UnicastSubject<$rc<UnicastState<O, U>>>
+    where
+      O: Observer + ?Sized,
+      U: SubscriptionLike,
+    {
+      type Item = O::Item;
+      type Err = O::Err;
+
+      #[inline]
+      fn next(&mut self, value: Self::Item) {
+        self.notify(Notification::Next(value))
+      }
+
+      #[inline]
+      fn error(&mut self, err: Self::Err) {
+        self.notify(Notification::Error(err))
+      }
+
+      #[inline]
+      fn complete(&mut self) { self.notify(Notification::Complete) }
+    }
+  };
+}
+
+impl_unicast!(MutRc);
+impl_unicast!(MutArc);
+
+impl<'a, Item, Err> Observable for LocalUnicastSubject<'a, Item, Err> {
+  type Item = Item;
+  type Err = Err;
+}
+
+impl<'a, Item, Err> LocalObservable<'a> for LocalUnicastSubject<'a, Item, Err>
+where
+  Err: From<UnicastError>,
+{
+  type Unsub = MutRc<SingleSubscription>;
+  fn actual_subscribe<O>(self, mut observer: O) -> Self::Unsub
+  where
+    O: Observer<Item = Self::Item, Err = Self::Err> + 'a,
+  {
+    let mut subscription = MutRc::own(SingleSubscription::default());
+    if self.0.rc_deref_mut().accept() {
+      self.drain(Box::new(observer), subscription.clone());
+    } else {
+      observer.error(UnicastError.into());
+      subscription.unsubscribe();
+    }
+    subscription
+  }
+}
+
+impl<Item, Err> Observable for SharedUnicastSubject<Item, Err> {
+  type Item = Item;
+  type Err = Err;
+}
+
+impl<Item, Err> SharedObservable for SharedUnicastSubject<Item, Err>
+where
+  Err: From<UnicastError>,
+{
+  type Unsub = MutArc<SingleSubscription>;
+  fn actual_subscribe<O>(self, mut observer: O) -> Self::Unsub
+  where
+    O: Observer<Item = Self::Item, Err = Self::Err> + Sync + Send + 'static,
+  {
+    let mut subscription = MutArc::own(SingleSubscription::default());
+    if self.0.rc_deref_mut().accept() {
+      self.drain(Box::new(observer), subscription.clone());
+    } else {
+      observer.error(UnicastError.into());
+      subscription.unsubscribe();
+    }
+    subscription
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use crate::prelude::*;
+  use crate::test_observer::TestObserver;
+
+  #[test]
+  fn replay_buffer() {
+    let mut subject = LocalUnicastSubject::<_, UnicastError>::new();
+    subject.next(1);
+    subject.next(2);
+
+    let observer = TestObserver::new();
+    subject.clone().actual_subscribe(observer.clone());
+    observer.assert_values(&[1, 2]).assert_not_complete();
+
+    subject.next(3);
+    subject.complete();
+    subject.next(4);
+    observer.assert_values(&[1, 2, 3]).assert_complete();
+  }
+
+  #[test]
+  fn replay_termination() {
+    let mut subject = LocalUnicastSubject::<i32, UnicastError>::new();
+    subject.next(1);
+    subject.complete();
+
+    let observer = TestObserver::new();
+    subject.clone().actual_subscribe(observer.clone());
+    observer.assert_values(&[1]).assert_complete();
+  }
+
+  #[test]
+  fn reject_second_observer() {
+    let subject = LocalUnicastSubject::<i32, UnicastError>::new();
+    let first = TestObserver::new();
+    let second = TestObserver::new();
+    subject.clone().actual_subscribe(first.clone());
+    let subscription = subject.actual_subscribe(second.clone());
+
+    first.assert_no_errors();
+    second.assert_error_matches(|e| *e == UnicastError);
+    assert!(subscription.is_closed());
+  }
+
+  #[test]
+  fn unsubscribe() {
+    let mut subject = LocalUnicastSubject::<_, UnicastError>::new();
+    let observer = TestObserver::new();
+    let mut subscription = subject.clone().actual_subscribe(observer.clone());
+    subject.next(1);
+    subscription.unsubscribe();
+    subject.next(2);
+
+    observer.assert_values(&[1]);
+    let late = TestObserver::new();
+    subject.clone().actual_subscribe(late.clone());
+    late.assert_error_matches(|e| *e == UnicastError);
+  }
+
+  #[test]
+  fn reentrant_next() {
+    let subject = LocalUnicastSubject::<_, UnicastError>::new();
+    let values = MutRc::own(vec![]);
+    let (mut c_subject, c_values) = (subject.clone(), values.clone());
+    subject.clone().subscribe_err(
+      move |v| {
+        c_values.rc_deref_mut().push(v);
+        if v < 3 {
+          c_subject.next(v + 1);
+        }
+      },
+      |_| {},
+    );
+    subject.clone().next(0);
+
+    assert_eq!(*values.rc_deref(), vec![0, 1, 2, 3]);
+  }
+
+  #[cfg(not(target_arch = "wasm32"))]
+  #[test]
+  fn shared() {
+    let subject = SharedUnicastSubject::<_, UnicastError>::new();
+    let handles = (0..4)
+      .map(|i| {
+        let mut subject = subject.clone();
+        std::thread::spawn(move || {
+          (0..25).for_each(|v| subject.next(i * 25 + v))
+        })
+      })
+      .collect::<Vec<_>>();
+
+    let observer = TestObserver::new();
+    subject
+      .clone()
+      .into_shared()
+      .actual_subscribe(observer.clone());
+    handles.into_iter().for_each(|h| h.join().unwrap());
+    subject.clone().complete();
+
+    observer.assert_complete();
+    let mut values = observer.values();
+    values.sort_unstable();
+    assert_eq!(values, (0..100).collect::<Vec<_>>());
+  }
+}
@@ -0,0 +1,331 @@
+//! An observer recording the notifications it receives, with assertions on
+//! them, enabled by the feature `testing`.
+use crate::prelude::*;
+use crate::scheduler::Instant;
+use std::fmt::Debug;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::Duration;
+
+type Clock = Arc<dyn Fn() -> Instant + Send + Sync>;
+type Records<Item, Err> = Arc<(Mutex<Vec<Record<Item, Err>>>, Condvar)>;
+
+/// An observer that records every notification it receives as a
+/// [`Notification`], stamped with the time of its clock if it has one.
+///
+/// `TestObserver` is a cheap handle, subscribe a clone and assert on the
+/// original. It can be used in both local and shared pipelines, it is `Send`
+/// and `Sync` as long as `Item` and `Err` are `Send`.
+///
+/// The assertions panic if they fail, and return the observer to be chained.
+///
+/// ```rust
+/// # use rxrust::prelude::*;
+/// use rxrust::test_observer::TestObserver;
+///
+/// let observer = TestObserver::new();
+/// observable::from_iter(1..4)
+///   .into_shared()
+///   .actual_subscribe(observer.clone());
+///
+/// observer
+///   .assert_values(&[1, 2, 3])
+///   .assert_no_errors()
+///   .assert_complete();
+/// ```
+pub struct TestObserver<Item, Err> {
+  record: Records<Item, Err>,
+  clock: Option<Clock>,
+}
+
+/// A notification received by a [`TestObserver`], and the time of receipt if
+/// the observer has a clock.
+pub type Record<Item, Err> = (Option<Instant>, Notification<Item, Err>);
+
+impl<Item, Err> Clone for TestObserver<Item, Err> {
+  fn clone(&self) -> Self {
+    TestObserver {
+      record: self.record.clone(),
+      clock: self.clock.clone(),
+    }
+  }
+}
+
+impl<Item, Err> Default for TestObserver<Item, Err> {
+  fn default() -> Self {
+    TestObserver {
+      record: Arc::new((Mutex::new(vec![]), Condvar::new())),
+      clock: None,
+    }
+  }
+}
+
+impl<Item, Err> TestObserver<Item, Err> {
+  /// Creates an observer without clock, its records have no timestamp.
+  #[inline]
+  pub fn new() -> Self { Self::default() }
+
+  /// Creates an observer stamping every record with the time returned by
+  /// `clock`, for example the `now` of a scheduler.
+  pub fn with_clock(
+    clock: impl Fn() -> Instant + Send + Sync + 'static,
+  ) -> Self {
+    TestObserver {
+      clock: Some(Arc::new(clock)),
+      ..Self::default()
+    }
+  }
+
+  /// All the notifications received, in the order they were received.
+  pub fn records(&self) -> MutexGuard<'_, Vec<Record<Item, Err>>> {
+    self.record.0.lock().unwrap()
+  }
+
+  /// The count of the values received.
+  pub fn value_count(&self) -> usize {
+    self
+      .records()
+      .iter()
+      .filter(|(_, n)| matches!(n, Notification::Next(_)))
+      .count()
+  }
+
+  /// Asserts the observer received exactly one complete notification, and
+  /// nothing after it.
+  pub fn assert_complete(&self) -> &Self {
+    let records = self.records();
+    let completes = records
+      .iter()
+      .filter(|(_, n)| matches!(n, Notification::Complete))
+      .count();
+    assert_eq!(completes, 1, "expected exactly one complete notification.");
+    assert!(
+      matches!(records.last(), Some((_, Notification::Complete))),
+      "received notifications after complete."
+    );
+    self
+  }
+
+  /// Asserts the observer did not complete.
+  pub fn assert_not_complete(&self) -> &Self {
+    let completed = self
+      .records()
+      .iter()
+      .any(|(_, n)| matches!(n, Notification::Complete));
+    assert!(!completed, "expected no complete notification.");
+    self
+  }
+
+  /// Asserts the observer received exactly one error, and `predicate`
+  /// returns true for it.
+  pub fn assert_error_matches(
+    &self,
+    predicate: impl FnOnce(&Err) -> bool,
+  ) -> &Self {
+    let records = self.records();
+    let mut errors = records.iter().filter_map(|(_, n)| match n {
+      Notification::Error(err) => Some(err),
+      _ => None,
+    });
+    let err = errors.next().expect("expected an error notification.");
+    assert!(
+      errors.next().is_none(),
+      "expected exactly one error notification."
+    );
+    assert!(predicate(err), "the error does not match.");
+    self
+  }
+
+  /// Asserts the observer did not receive any error.
+  pub fn assert_no_errors(&self) -> &Self {
+    let errors = self
+      .records()
+      .iter()
+      .filter(|(_, n)| matches!(n, Notification::Error(_)))
+      .count();
+    assert_eq!(errors, 0, "expected no error notification.");
+    self
+  }
+
+  /// Blocks the current thread until the observer received at least `count`
+  /// values or a terminal notification, panics if `timeout` elapses first.
+  pub fn await_count(&self, count: usize, timeout: Duration) -> &Self {
+    let (lock, cvar) = &*self.record;
+    let records = lock.lock().unwrap();
+    let (_records, result) = cvar
+      .wait_timeout_while(records, timeout, |records| {
+        let values = records
+          .iter()
+          .filter(|(_, n)| matches!(n, Notification::Next(_)))
+          .count();
+        values < count && !records.iter().any(|(_, n)| n.is_terminal())
+      })
+      .unwrap();
+    assert!(
+      !result.timed_out(),
+      "timed out waiting for {} values after {:?}.",
+      count,
+      timeout
+    );
+    self
+  }
+
+  /// Asserts `subscription` is closed, and that the observer was not
+  /// terminated, so the subscription was closed by unsubscribing.
+  pub fn assert_unsubscribed(
+    &self,
+    subscription: &impl SubscriptionLike,
+  ) -> &Self {
+    assert!(subscription.is_closed(), "the subscription is not closed.");
+    let terminated = self.records().iter().any(|(_, n)| n.is_terminal());
+    assert!(!terminated, "the observer is terminated, not unsubscribed.");
+    self
+  }
+
+  fn push(&self, notification: Notification<Item, Err>) {
+    let at = self.clock.as_ref().map(|clock| clock());
+    let (lock, cvar) = &*self.record;
+    lock.lock().unwrap().push((at, notification));
+    cvar.notify_all();
+  }
+}
+
+impl<Item, Err> TestObserver<Item, Err>
+where
+  Item: Clone,
+{
+  /// All the values received, in the order they were received.
+  pub fn values(&self) -> Vec<Item> {
+    self
+      .records()
+      .iter()
+      .filter_map(|(_, n)| match n {
+        Notification::Next(v) => Some(v.clone()),
+        _ => None,
+      })
+      .collect()
+  }
+}
+
+impl<Item, Err> TestObserver<Item, Err>
+where
+  Item: Clone + PartialEq + Debug,
+{
+  /// Asserts the observer received exactly `values`, in order.
+  pub fn assert_values(&self, values: &[Item]) -> &Self {
+    assert_eq!(self.values(), values, "the values are not equal.");
+    self
+  }
+}
+
+impl<Item, Err> Observer for TestObserver<Item, Err> {
+  type Item = Item;
+  type Err = Err;
+
+  fn next(&mut self, value: Item) { self.push(Notification::Next(value)) }
+
+  fn error(&mut self, err: Err) { self.push(Notification::Error(err)) }
+
+  fn complete(&mut self) { self.push(Notification::Complete) }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::test_scheduler::TestScheduler;
+
+  #[test]
+  fn values_and_complete() {
+    let observer = TestObserver::new();
+    LocalObservable::actual_subscribe(
+      observable::from_iter(0..3),
+      observer.clone(),
+    );
+
+    observer
+      .assert_values(&[0, 1, 2])
+      .assert_no_errors()
+      .assert_complete();
+    assert_eq!(observer.value_count(), 3);
+  }
+
+  #[test]
+  fn error() {
+    let observer = TestObserver::<(), _>::new();
+    LocalObservable::actual_subscribe(
+      observable::throw("oops"),
+      observer.clone(),
+    );
+
+    observer
+      .assert_values(&[])
+      .assert_not_complete()
+      .assert_error_matches(|e| *e == "oops");
+  }
+
+  #[test]
+  #[should_panic(expected = "expected no error notification.")]
+  fn assert_no_errors_fails() {
+    let observer = TestObserver::<(), _>::new();
+    LocalObservable::actual_subscribe(observable::throw(()), observer.clone());
+    observer.assert_no_errors();
+  }
+
+  #[test]
+  fn unsubscribed() {
+    let observer = TestObserver::<i32, ()>::new();
+    let mut subject = LocalSubject::new();
+    let mut subscription = subject.clone().actual_subscribe(observer.clone());
+    subject.next(1);
+    subscription.unsubscribe();
+    subject.next(2);
+
+    observer
+      .assert_values(&[1])
+      .assert_unsubscribed(&subscription);
+  }
+
+  #[test]
+  fn timestamps() {
+    let scheduler = TestScheduler::default();
+    let start = scheduler.now();
+    let c_scheduler = scheduler.clone();
+    let observer = TestObserver::with_clock(move || c_scheduler.now());
+    LocalObservable::actual_subscribe(
+      observable::interval(Duration::from_millis(10), scheduler.clone())
+        .take(2),
+      observer.clone(),
+    );
+
+    scheduler.advance_by(Duration::from_millis(50));
+    let stamps: Vec<_> = observer
+      .records()
+      .iter()
+      .map(|(at, _)| at.unwrap() - start)
+      .collect();
+    assert_eq!(
+      stamps,
+      vec![
+        Duration::from_millis(10),
+        Duration::from_millis(20),
+        Duration::from_millis(20)
+      ]
+    );
+  }
+
+  #[cfg(not(target_arch = "wasm32"))]
+  #[test]
+  fn await_count_shared() {
+    use futures::executor::ThreadPool;
+    let pool = ThreadPool::new().unwrap();
+    let observer = TestObserver::new();
+    observable::interval(Duration::from_millis(1), pool)
+      .take(3)
+      .into_shared()
+      .actual_subscribe(observer.clone());
+
+    observer
+      .await_count(3, Duration::from_secs(5))
+      .assert_no_errors();
+    assert!(observer.value_count() >= 3);
+  }
+}
@@ -1,57 +1,308 @@
-#![cfg(test)]
-use crate::prelude::{LocalScheduler, SpawnHandle, SubscriptionLike};
+//! A virtual-time scheduler to test time-based pipelines deterministically,
+//! enabled by the feature `testing`.
+use crate::prelude::*;
 use crate::scheduler::Instant;
 use futures::future::AbortHandle;
+use std::cell::RefCell;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap};
 use std::future::Future;
-use std::ops::{Add, Sub};
-use std::sync::{Arc, Mutex, RwLock};
+use std::sync::{
+  Arc, Mutex,
+  atomic::{AtomicUsize, Ordering as AtomicOrdering},
+};
+use std::thread::{self, ThreadId};
 use std::time::Duration;
 
+/// A scheduler that runs its tasks on a virtual clock.
+///
+/// Nothing runs until the clock is driven by [`advance_by`], [`advance_to`],
+/// [`run_until_idle`] or [`flush`], then every due task runs on the calling
+/// thread in timestamp order, tasks due at the same time run in the order
+/// they were scheduled. While a task runs, [`now`] returns the time the task
+/// was due.
+///
+/// `TestScheduler` implements both `LocalScheduler` and `SharedScheduler`.
+/// Tasks scheduled by the `LocalScheduler` must be run on the thread that
+/// scheduled them. Futures passed to `spawn` are driven to completion
+/// immediately.
+///
+/// ```rust
+/// # use rxrust::prelude::*;
+/// use rxrust::test_scheduler::TestScheduler;
+/// use std::time::Duration;
+///
+/// let scheduler = TestScheduler::default();
+/// let ticks = MutRc::own(vec![]);
+/// let c_ticks = ticks.clone();
+/// observable::interval(Duration::from_secs(1), scheduler.clone())
+///   .take(3)
+///   .subscribe(move |v| c_ticks.rc_deref_mut().push(v));
+///
+/// scheduler.advance_by(Duration::from_secs(2));
+/// assert_eq!(&*ticks.rc_deref(), &[0, 1]);
+/// scheduler.advance_by(Duration::from_secs(1));
+/// assert_eq!(&*ticks.rc_deref(), &[0, 1, 2]);
+/// ```
+///
+/// [`advance_by`]: TestScheduler::advance_by
+/// [`advance_to`]: TestScheduler::advance_to
+/// [`run_until_idle`]: TestScheduler::run_until_idle
+/// [`flush`]: TestScheduler::flush
+/// [`now`]: TestScheduler::now
 #[derive(Clone)]
-pub struct ManualScheduler {
-  clock: Arc<RwLock<FakeClock>>,
-  repeating_task: Arc<RwLock<Vec<Arc<RwLock<RepeatingTask>>>>>,
-  oneshot_tasks: Arc<RwLock<Vec<OneshotTask>>>,
+pub struct TestScheduler {
+  inner: Arc<Mutex<Inner>>,
 }
 
-struct FakeClock {
-  current_time: Instant,
+struct Inner {
+  id: usize,
+  now: Instant,
+  seq: u64,
+  queue: BinaryHeap<Reverse<Entry>>,
 }
 
-impl FakeClock {
-  fn instant(&self) -> Instant { self.current_time }
+struct Entry {
+  due: Instant,
+  seq: u64,
+  period: Option<(Duration, IntervalPolicy)>,
+  invokes: usize,
+  cancel: SpawnHandle,
+  task: Task,
+}
+
+enum Task {
+  Shared(Box<dyn FnMut(usize) + Send>),
+  /// The task is not `Send`, so it is kept in the thread local storage of the
+  /// thread which scheduled it, with the key `(scheduler id, key)`.
+  Local {
+    thread: ThreadId,
+    key: u64,
+  },
+}
 
-  pub fn new(time: Instant) -> FakeClock { FakeClock { current_time: time } }
+type LocalTask = Box<dyn FnMut(usize)>;
 
-  pub fn advance(&mut self, duration: Duration) {
-    self.current_time = self.current_time.add(duration);
+thread_local! {
+  static LOCAL_TASKS: RefCell<HashMap<(usize, u64), LocalTask>> =
+    RefCell::new(HashMap::new());
+}
+
+static SCHEDULER_ID: AtomicUsize = AtomicUsize::new(0);
+
+impl PartialEq for Entry {
+  fn eq(&self, other: &Self) -> bool { self.cmp(other) == Ordering::Equal }
+}
+
+impl Eq for Entry {}
+
+impl PartialOrd for Entry {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
   }
 }
 
-struct RepeatingTask {
-  task: Box<dyn FnMut(usize)>,
-  delay: Duration,
-  last_time: Instant,
-  invokes: Arc<RwLock<usize>>,
-  cancel: SpawnHandle,
+impl Ord for Entry {
+  fn cmp(&self, other: &Self) -> Ordering {
+    (self.due, self.seq).cmp(&(other.due, other.seq))
+  }
+}
+
+impl Default for TestScheduler {
+  #[inline]
+  fn default() -> Self { Self::new(Instant::now()) }
+}
+
+impl TestScheduler {
+  /// Creates a scheduler whose virtual clock starts at `start`.
+  pub fn new(start: Instant) -> Self {
+    TestScheduler {
+      inner: Arc::new(Mutex::new(Inner {
+        id: SCHEDULER_ID.fetch_add(1, AtomicOrdering::Relaxed),
+        now: start,
+        seq: 0,
+        queue: BinaryHeap::new(),
+      })),
+    }
+  }
+
+  /// Returns the current virtual time.
+  #[inline]
+  pub fn now(&self) -> Instant { self.inner.lock().unwrap().now }
+
+  /// Advances the virtual clock by `duration`, running every task that
+  /// becomes due.
+  pub fn advance_by(&self, duration: Duration) {
+    let to = self.inner.lock().unwrap().now + duration;
+    self.advance_to(to);
+  }
+
+  /// Advances the virtual clock to `time`, running every task that becomes
+  /// due. The clock never goes backwards.
+  pub fn advance_to(&self, time: Instant) {
+    while let Some(entry) = self.pop_due(time) {
+      self.run_entry(entry);
+    }
+    let mut inner = self.inner.lock().unwrap();
+    if inner.now < time {
+      inner.now = time;
+    }
+  }
+
+  /// Runs every task that is due at the current virtual time, including the
+  /// ones scheduled by the tasks run, without advancing the clock.
+  pub fn run_until_idle(&self) {
+    let now = self.inner.lock().unwrap().now;
+    self.advance_to(now);
+  }
+
+  /// Advances the virtual clock until no one-shot task remains. Repeating
+  /// tasks run along the way whenever they are due, but don't keep `flush`
+  /// going by themselves.
+  pub fn flush(&self) {
+    loop {
+      let next = {
+        let inner = self.inner.lock().unwrap();
+        inner
+          .queue
+          .iter()
+          .filter(|Reverse(e)| e.period.is_none())
+          .map(|Reverse(e)| e.due)
+          .min()
+      };
+      match next {
+        Some(due) => self.advance_to(due),
+        None => break,
+      }
+    }
+  }
+
+  /// Returns the count of the tasks waiting to run, cancelled tasks which
+  /// are not removed yet are counted.
+  pub fn pending_tasks(&self) -> usize {
+    self.inner.lock().unwrap().queue.len()
+  }
+
+  fn pop_due(&self, time: Instant) -> Option<Entry> {
+    let mut inner = self.inner.lock().unwrap();
+    let due = inner.queue.peek().is_some_and(|Reverse(e)| e.due <= time);
+    if !due {
+      return None;
+    }
+    let Reverse(entry) = inner.queue.pop().unwrap();
+    if inner.now < entry.due {
+      inner.now = entry.due;
+    }
+    Some(entry)
+  }
+
+  fn run_entry(&self, mut entry: Entry) {
+    let id = self.inner.lock().unwrap().id;
+    if !entry.cancel.is_closed() {
+      match &mut entry.task {
+        Task::Shared(task) => task(entry.invokes),
+        Task::Local { thread, key } => {
+          assert_eq!(
+            *thread,
+            thread::current().id(),
+            "a local task must run on the thread which scheduled it."
+          );
+          let task = LOCAL_TASKS.with(|m| m.borrow_mut().remove(&(id, *key)));
+          if let Some(mut task) = task {
+            task(entry.invokes);
+            LOCAL_TASKS.with(|m| m.borrow_mut().insert((id, *key), task));
+          }
+        }
+      }
+    }
+
+    match entry.period {
+      Some((period, policy)) if !entry.cancel.is_closed() => {
+        // Keeps the sequence, so repeating tasks ticking at the same time
+        // always run in the order they were scheduled.
+        let now = self.inner.lock().unwrap().now;
+        entry.due = policy.next_deadline(entry.due, period, now);
+        entry.invokes += 1;
+        self.inner.lock().unwrap().queue.push(Reverse(entry));
+      }
+      _ => {
+        if let Task::Local { thread, key } = entry.task {
+          if thread == thread::current().id() {
+            LOCAL_TASKS.with(|m| m.borrow_mut().remove(&(id, key)));
+          }
+        }
+      }
+    }
+  }
+
+  fn push(&self, mut entry: Entry) {
+    let mut inner = self.inner.lock().unwrap();
+    entry.seq = inner.seq;
+    inner.seq += 1;
+    inner.queue.push(Reverse(entry));
+  }
+
+  fn add_task(
+    &self,
+    task: Task,
+    due: Instant,
+    period: Option<(Duration, IntervalPolicy)>,
+  ) -> SpawnHandle {
+    let cancel = SpawnHandle::new(AbortHandle::new_pair().0);
+    self.push(Entry {
+      due,
+      seq: 0,
+      period,
+      invokes: 0,
+      cancel: cancel.clone(),
+      task,
+    });
+    cancel
+  }
+
+  fn add_local_task(
+    &self,
+    task: LocalTask,
+    due: Instant,
+    period: Option<(Duration, IntervalPolicy)>,
+  ) -> SpawnHandle {
+    let (id, key) = {
+      let mut inner = self.inner.lock().unwrap();
+      inner.seq += 1;
+      (inner.id, inner.seq)
+    };
+    LOCAL_TASKS.with(|m| m.borrow_mut().insert((id, key), task));
+    let thread = thread::current().id();
+    self.add_task(Task::Local { thread, key }, due, period)
+  }
+
+  fn due_time(&self, delay: Option<Duration>) -> Instant {
+    self.inner.lock().unwrap().now + delay.unwrap_or_default()
+  }
+
+  fn first_tick(&self, period: Duration, at: Option<Instant>) -> Instant {
+    at.unwrap_or_else(|| self.due_time(Some(period)))
+  }
 }
 
-impl RepeatingTask {
-  pub fn invoke(&mut self) {
-    let invokes = *self.invokes.read().unwrap();
-    (self.task)(invokes);
-    *self.invokes.write().unwrap() += 1;
+impl Drop for Inner {
+  fn drop(&mut self) {
+    let id = self.id;
+    let _ = LOCAL_TASKS
+      .try_with(|m| m.borrow_mut().retain(|(task_id, _), _| *task_id != id));
   }
 }
 
-struct OneshotTask {
-  task: Box<dyn FnOnce()>,
-  delay: Duration,
-  start: Instant,
-  cancel: SpawnHandle,
+fn once<T>(task: impl FnOnce(T), state: T) -> impl FnMut(usize) {
+  let mut task = Some((task, state));
+  move |_| {
+    if let Some((task, state)) = task.take() {
+      task(state)
+    }
+  }
 }
 
-impl LocalScheduler for ManualScheduler {
+impl LocalScheduler for TestScheduler {
   fn spawn<Fut>(&self, future: Fut)
   where
     Fut: Future<Output = ()> + 'static,
@@ -59,208 +310,257 @@ impl LocalScheduler for ManualScheduler {
     futures::executor::block_on(future);
   }
 
+  #[inline]
+  fn now(&self) -> Instant { TestScheduler::now(self) }
+
   fn schedule<S: 'static>(
     &self,
     task: impl FnOnce(S) + 'static,
     delay: Option<Duration>,
     state: S,
   ) -> SpawnHandle {
-    let handle = SpawnHandle::new(AbortHandle::new_pair().0);
-    (*self.oneshot_tasks.write().unwrap()).push(OneshotTask {
-      task: Box::new(|| {
-        task(state);
-      }),
-      delay: delay.unwrap_or_else(|| Duration::from_micros(0)),
-      start: (*self.clock.read().unwrap()).instant(),
-      cancel: handle.clone(),
-    });
-    handle
+    let due = self.due_time(delay);
+    self.add_local_task(Box::new(once(task, state)), due, None)
   }
 
   fn schedule_repeating(
     &self,
     task: impl FnMut(usize) + 'static,
-    delay: Duration,
+    time_between: Duration,
     at: Option<Instant>,
+    policy: IntervalPolicy,
   ) -> SpawnHandle {
-    let handle = SpawnHandle::new(AbortHandle::new_pair().0);
-    (*self.repeating_task.write().unwrap()).push(Arc::new(RwLock::new(
-      RepeatingTask {
-        task: Box::new(task),
-        invokes: Arc::new(RwLock::new(0)),
-        delay,
-        last_time: at
-          .map(|t| t.sub(delay))
-          .unwrap_or_else(|| (*self.clock.read().unwrap()).instant()),
-        cancel: handle.clone(),
-      },
-    )));
-    handle
+    let due = self.first_tick(time_between, at);
+    self.add_local_task(Box::new(task), due, Some((time_between, policy)))
   }
 }
 
-impl ManualScheduler {
-  pub fn new(now: Instant) -> ManualScheduler {
-    ManualScheduler {
-      clock: Arc::new(RwLock::new(FakeClock::new(now))),
-      repeating_task: Arc::new(RwLock::new(vec![])),
-      oneshot_tasks: Arc::new(RwLock::new(vec![])),
-    }
+#[cfg(not(all(target_arch = "wasm32")))]
+impl SharedScheduler for TestScheduler {
+  fn spawn<Fut>(&self, future: Fut)
+  where
+    Fut: Future<Output = ()> + Send + 'static,
+  {
+    futures::executor::block_on(future);
   }
 
-  pub fn now() -> ManualScheduler { ManualScheduler::new(Instant::now()) }
-
-  pub fn advance(&self, time: Duration) {
-    self.clock.write().unwrap().advance(time);
-  }
+  #[inline]
+  fn now(&self) -> Instant { TestScheduler::now(self) }
 
-  pub fn advance_and_run(&self, advance_by: Duration, times: usize) {
-    for _ in 0..times {
-      self.advance(advance_by);
-      self.run_tasks();
-    }
+  fn schedule<S: Send + 'static>(
+    &self,
+    task: impl FnOnce(S) + Send + 'static,
+    delay: Option<Duration>,
+    state: S,
+  ) -> SpawnHandle {
+    let due = self.due_time(delay);
+    self.add_task(Task::Shared(Box::new(once(task, state))), due, None)
   }
 
-  pub fn run_tasks(&self) {
-    // Minor race condition below that would result in incomplete processing if
-    // multiple threads cancel or add tasks while in loop, resolves on next run
-    let oneshots = (*self.oneshot_tasks.read().unwrap()).len();
-    for _ in 0..oneshots {
-      let t = (*self.oneshot_tasks.write().unwrap()).pop().unwrap();
-      if !t.cancel.is_closed() {
-        let next_time: Instant = t.start.add(t.delay);
-        let clock_time = (*self.clock.read().unwrap()).instant();
-        if next_time < clock_time {
-          (t.task)();
-        } else {
-          (*self.oneshot_tasks.write().unwrap()).push(t);
-        }
-      }
-    }
-
-    // Minor race condition below that would result in incomplete processing if
-    // multiple threads cancel or add tasks while in loop, resolves on next run
-    let repeated = (*self.repeating_task.read().unwrap()).len();
-    for _ in 0..repeated {
-      let t = (*self.repeating_task.write().unwrap()).pop().unwrap();
-      if !t.read().unwrap().cancel.is_closed() {
-        self.repeating_task.write().unwrap().push(t);
-      }
-    }
-
-    for task in &(*self.repeating_task.read().unwrap()) {
-      let task_c = Arc::clone(task);
-      let delay = (*task_c.read().unwrap()).delay;
-
-      let mut prev = task_c.read().unwrap().last_time;
-      let next_time: Instant = prev.add(delay);
-      let clock_time = (*self.clock.read().unwrap()).instant();
-      if next_time <= clock_time {
-        let delay_millis = delay.as_millis();
-        let passed_millis = clock_time.sub(prev).as_millis();
-        let wanted_invokes = passed_millis / delay_millis;
-        for _ in 0..wanted_invokes {
-          task_c.write().unwrap().invoke();
-          prev = prev.add(delay);
-        }
-        if wanted_invokes > 0 {
-          (*task_c.write().unwrap()).last_time = prev;
-        }
-      }
-    }
+  fn schedule_repeating(
+    &self,
+    task: impl FnMut(usize) + Send + 'static,
+    time_between: Duration,
+    at: Option<Instant>,
+    policy: IntervalPolicy,
+  ) -> SpawnHandle {
+    let due = self.first_tick(time_between, at);
+    self.add_task(
+      Task::Shared(Box::new(task)),
+      due,
+      Some((time_between, policy)),
+    )
   }
 }
+
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::test_scheduler::ManualScheduler;
+  use std::ops::Add;
 
   #[test]
   fn spawns_sync() {
-    let scheduler = ManualScheduler::now();
+    let scheduler = TestScheduler::default();
     let invokes = Arc::new(Mutex::new(0));
     let invokes_c = invokes.clone();
     let fut = futures::future::lazy(move |_| *invokes_c.lock().unwrap() += 1);
-    scheduler.spawn(fut);
+    LocalScheduler::spawn(&scheduler, fut);
     assert_eq!(1, *invokes.lock().unwrap());
   }
 
   #[test]
   fn schedule_repeating() {
     let time = Instant::now();
-    let scheduler = ManualScheduler::new(time);
+    let scheduler = TestScheduler::new(time);
     let invokes = Arc::new(Mutex::new(0));
     let invokes_c = invokes.clone();
     let delay = Duration::from_millis(100);
-    let mut handle = scheduler.schedule_repeating(
+    let mut handle = LocalScheduler::schedule_repeating(
+      &scheduler,
       move |_| *invokes_c.clone().lock().unwrap() += 1,
       delay,
       Some(time.add(Duration::from_millis(5))),
+      IntervalPolicy::Burst,
     );
-    scheduler.run_tasks();
+    scheduler.run_until_idle();
     assert_eq!(0, *invokes.lock().unwrap());
-    scheduler.advance(Duration::from_millis(5));
-    scheduler.run_tasks();
+    scheduler.advance_by(Duration::from_millis(5));
     assert_eq!(1, *invokes.lock().unwrap());
-    scheduler.advance(delay);
-    scheduler.run_tasks();
+    scheduler.advance_by(delay);
     assert_eq!(2, *invokes.lock().unwrap());
-    scheduler.run_tasks();
-    scheduler.advance(delay);
-    scheduler.run_tasks();
+    scheduler.run_until_idle();
+    scheduler.advance_by(delay);
     assert_eq!(3, *invokes.lock().unwrap());
-    scheduler.advance(10 * delay);
-    scheduler.run_tasks();
+    scheduler.advance_by(10 * delay);
     assert_eq!(13, *invokes.lock().unwrap());
     handle.unsubscribe();
     assert!(handle.is_closed());
-    scheduler.advance(10 * delay);
-    scheduler.run_tasks();
+    scheduler.advance_by(10 * delay);
     assert_eq!(13, *invokes.lock().unwrap());
+    assert_eq!(scheduler.pending_tasks(), 0);
   }
 
   #[test]
   fn schedule() {
-    let scheduler = ManualScheduler::now();
+    let scheduler = TestScheduler::default();
     let invokes = Arc::new(Mutex::new(0));
     let invokes_c = invokes.clone();
     let delay = Duration::from_millis(100);
-    scheduler.schedule(
+    LocalScheduler::schedule(
+      &scheduler,
       move |_| *invokes_c.lock().unwrap() += 1,
       Some(delay),
       1,
     );
-    scheduler.advance(delay);
-    scheduler.run_tasks();
+    scheduler.advance_by(delay - Duration::from_millis(1));
     assert_eq!(0, *invokes.lock().unwrap());
-    scheduler.advance(Duration::from_millis(1));
-    scheduler.run_tasks();
-    scheduler.advance(delay);
-    scheduler.run_tasks();
+    scheduler.advance_by(Duration::from_millis(1));
     assert_eq!(1, *invokes.lock().unwrap());
-    scheduler.advance(10 * delay);
-    scheduler.run_tasks();
+    scheduler.advance_by(10 * delay);
     assert_eq!(1, *invokes.lock().unwrap());
   }
 
   #[test]
   fn schedule_no_schedule_after_unsub() {
-    let scheduler = ManualScheduler::now();
+    let scheduler = TestScheduler::default();
     let invokes = Arc::new(Mutex::new(0));
     let invokes_c = invokes.clone();
     let delay = Duration::from_millis(100);
-    let mut handle = scheduler.schedule(
+    let mut handle = LocalScheduler::schedule(
+      &scheduler,
       move |_| *invokes_c.lock().unwrap() += 1,
       Some(delay),
       1,
     );
-    scheduler.advance(delay);
-    scheduler.run_tasks();
+    scheduler.advance_by(delay / 2);
     assert_eq!(0, *invokes.lock().unwrap());
     handle.unsubscribe();
     assert!(handle.is_closed());
-    scheduler.advance(Duration::from_millis(1));
-    scheduler.run_tasks();
+    scheduler.advance_by(delay);
     assert_eq!(0, *invokes.lock().unwrap());
   }
+
+  #[test]
+  fn timestamp_order() {
+    let start = Instant::now();
+    let scheduler = TestScheduler::new(start);
+    let log = MutRc::own(vec![]);
+    let push = |name: &'static str, delay: u64| {
+      let log = log.clone();
+      let c_scheduler = scheduler.clone();
+      LocalScheduler::schedule(
+        &scheduler,
+        move |_| {
+          let at = c_scheduler.now() - start;
+          log.rc_deref_mut().push((name, at.as_millis()))
+        },
+        Some(Duration::from_millis(delay)),
+        (),
+      );
+    };
+    push("c", 30);
+    push("a", 10);
+    push("b", 20);
+    push("a2", 10);
+
+    scheduler.advance_by(Duration::from_millis(100));
+    assert_eq!(
+      &*log.rc_deref(),
+      &[("a", 10), ("a2", 10), ("b", 20), ("c", 30)]
+    );
+    assert_eq!(scheduler.now() - start, Duration::from_millis(100));
+  }
+
+  #[test]
+  fn nested_schedule_in_same_advance() {
+    let scheduler = TestScheduler::default();
+    let log = MutRc::own(vec![]);
+    let (c_log, c_scheduler) = (log.clone(), scheduler.clone());
+    LocalScheduler::schedule(
+      &scheduler,
+      move |_| {
+        c_log.rc_deref_mut().push(1);
+        let c_log = c_log.clone();
+        LocalScheduler::schedule(
+          &c_scheduler,
+          move |_| c_log.rc_deref_mut().push(2),
+          Some(Duration::from_millis(5)),
+          (),
+        );
+      },
+      Some(Duration::from_millis(5)),
+      (),
+    );
+
+    scheduler.advance_by(Duration::from_millis(10));
+    assert_eq!(&*log.rc_deref(), &[1, 2]);
+  }
+
+  #[test]
+  fn run_until_idle_and_flush() {
+    let scheduler = TestScheduler::default();
+    let log = MutRc::own(vec![]);
+    let c_log = log.clone();
+    LocalScheduler::schedule(
+      &scheduler,
+      move |v| c_log.rc_deref_mut().push(v),
+      None,
+      0,
+    );
+    let c_log = log.clone();
+    LocalScheduler::schedule(
+      &scheduler,
+      move |v| c_log.rc_deref_mut().push(v),
+      Some(Duration::from_secs(60)),
+      1,
+    );
+
+    scheduler.run_until_idle();
+    assert_eq!(&*log.rc_deref(), &[0]);
+    scheduler.flush();
+    assert_eq!(&*log.rc_deref(), &[0, 1]);
+    assert_eq!(scheduler.pending_tasks(), 0);
+  }
+
+  #[cfg(not(target_arch = "wasm32"))]
+  #[test]
+  fn shared() {
+    let scheduler = TestScheduler::default();
+    let sum = Arc::new(Mutex::new(0));
+    let c_sum = sum.clone();
+    let c_scheduler = scheduler.clone();
+    std::thread::spawn(move || {
+      observable::interval(Duration::from_millis(10), c_scheduler)
+        .take(5)
+        .into_shared()
+        .subscribe(move |v| *c_sum.lock().unwrap() += v);
+    })
+    .join()
+    .unwrap();
+
+    scheduler.advance_by(Duration::from_millis(25));
+    assert_eq!(*sum.lock().unwrap(), 1);
+    scheduler.advance_by(Duration::from_millis(100));
+    assert_eq!(*sum.lock().unwrap(), 10);
+  }
 }